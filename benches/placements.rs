@@ -0,0 +1,234 @@
+//! Drops millions of pieces straight down onto a board, once with the
+//! bitboard grid and once with a grid of one `c_int` per cell checked
+//! through freshly collected cell positions, moving each block in place
+//! the way the game used to, and compares the two.
+//!
+//! Run with `cargo bench`.
+
+use std::{collections::HashMap, hint::black_box, os::raw::c_int, time::Instant};
+
+use tetris::{block::Block, grid::Grid, pieces, position::Position, rng::Rng};
+
+const PLACEMENTS: usize = 2_000_000;
+const ROWS: c_int = 20;
+const COLUMNS: c_int = 10;
+
+/// A placement: which piece, in which rotation state, dropped from which
+/// column.
+#[derive(Clone, Copy)]
+struct Placement {
+    piece: usize,
+    rotation: c_int,
+    column: c_int,
+}
+
+fn main() {
+    let pieces = pieces::standard();
+    let placements = placements(&pieces);
+
+    let start = Instant::now();
+    let bitboard_lines = place_on_bitboard(&pieces, &placements);
+    let bitboard = start.elapsed();
+
+    let start = Instant::now();
+    let cell_lines = place_on_cells(&pieces, &placements);
+    let cells = start.elapsed();
+
+    assert_eq!(bitboard_lines, cell_lines, "both grids must agree");
+
+    println!("{PLACEMENTS} placements, {bitboard_lines} lines cleared");
+    println!(
+        "bitboard: {:>8.1} ms, {:>6.1} M placements/s",
+        bitboard.as_secs_f64() * 1000.0,
+        PLACEMENTS as f64 / bitboard.as_secs_f64() / 1e6
+    );
+    println!(
+        "cells:    {:>8.1} ms, {:>6.1} M placements/s",
+        cells.as_secs_f64() * 1000.0,
+        PLACEMENTS as f64 / cells.as_secs_f64() / 1e6
+    );
+    println!(
+        "speedup:  {:.1}x",
+        cells.as_secs_f64() / bitboard.as_secs_f64()
+    );
+}
+
+/// Random placements with every piece kept inside the board.
+fn placements(pieces: &[Block]) -> Vec<Placement> {
+    let mut rng = Rng::new(1);
+
+    (0..PLACEMENTS)
+        .map(|_| {
+            let piece = rng.next_below(pieces.len());
+            let mut block = pieces[piece].clone();
+            let rotation = rng.next_below(block.rotation_count() as usize) as c_int;
+            for _ in 0..rotation {
+                block.rotate();
+            }
+
+            let mask = block.mask();
+            let columns = (COLUMNS - (mask.right - mask.left)) as usize;
+            let column = rng.next_below(columns) as c_int - mask.left;
+
+            Placement {
+                piece,
+                rotation,
+                column,
+            }
+        })
+        .collect()
+}
+
+fn place_on_bitboard(pieces: &[Block], placements: &[Placement]) -> c_int {
+    let masks: Vec<Vec<_>> = pieces
+        .iter()
+        .map(|piece| {
+            let mut block = piece.clone();
+            (0..block.rotation_count())
+                .map(|_| {
+                    let mask = block.mask().clone();
+                    block.rotate();
+                    mask
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut grid = Grid::new(ROWS, COLUMNS, 0);
+    let mut lines = 0;
+    for placement in placements {
+        let mask = &masks[placement.piece][placement.rotation as usize];
+        if !grid.fits(mask, 0, placement.column) {
+            grid.initialize();
+        }
+
+        let row = grid.drop_distance(mask, 0, placement.column);
+        grid.place(mask, row, placement.column, placement.piece as c_int + 1);
+        lines += grid.clear_full_rows();
+    }
+
+    black_box(lines)
+}
+
+/// The previous representation: a `c_int` per cell, and blocks whose
+/// rotation states are looked up in a map and offset into a new `Vec` for
+/// every check, as `Grid` and `Block` used to work.
+struct CellBlock {
+    cells: HashMap<c_int, Vec<Position>>,
+    rotation_state: c_int,
+    row_offset: c_int,
+    column_offset: c_int,
+}
+
+impl CellBlock {
+    fn new(block: &Block) -> Self {
+        let mut block = block.clone();
+        let offset = Position::new(block.row_offset(), block.column_offset());
+        let cells = (0..block.rotation_count())
+            .map(|rotation| {
+                let cells = block
+                    .get_cell_positions()
+                    .into_iter()
+                    .map(|cell| Position::new(cell.row - offset.row, cell.column - offset.column))
+                    .collect();
+                block.rotate();
+                (rotation, cells)
+            })
+            .collect();
+
+        Self {
+            cells,
+            rotation_state: 0,
+            row_offset: 0,
+            column_offset: 0,
+        }
+    }
+
+    fn get_cell_positions(&self) -> Vec<Position> {
+        self.cells[&self.rotation_state]
+            .iter()
+            .map(|cell| Position::new(cell.row + self.row_offset, cell.column + self.column_offset))
+            .collect()
+    }
+}
+
+struct CellGrid {
+    cells: Vec<Vec<c_int>>,
+}
+
+impl CellGrid {
+    fn new() -> Self {
+        Self {
+            cells: vec![vec![0; COLUMNS as usize]; ROWS as usize],
+        }
+    }
+
+    fn collides(&self, block: &CellBlock) -> bool {
+        let outside = block.get_cell_positions().iter().any(|position| {
+            position.row < 0
+                || position.row >= ROWS
+                || position.column < 0
+                || position.column >= COLUMNS
+        });
+
+        outside
+            || block
+                .get_cell_positions()
+                .iter()
+                .any(|position| self.cells[position.row as usize][position.column as usize] != 0)
+    }
+
+    /// Moves `block` down until it lands, as the old game moved the
+    /// current block in place.
+    fn drop(&self, block: &mut CellBlock) {
+        loop {
+            block.row_offset += 1;
+            if self.collides(block) {
+                block.row_offset -= 1;
+                return;
+            }
+        }
+    }
+
+    fn clear_full_rows(&mut self) -> c_int {
+        let mut completed = 0;
+        for row in (0..ROWS as usize).rev() {
+            if self.cells[row].iter().all(|&cell| cell != 0) {
+                self.cells[row].fill(0);
+                completed += 1;
+            } else if completed > 0 {
+                for column in 0..COLUMNS as usize {
+                    self.cells[row + completed as usize][column] = self.cells[row][column];
+                    self.cells[row][column] = 0;
+                }
+            }
+        }
+
+        completed
+    }
+}
+
+fn place_on_cells(pieces: &[Block], placements: &[Placement]) -> c_int {
+    let mut blocks: Vec<CellBlock> = pieces.iter().map(CellBlock::new).collect();
+
+    let mut grid = CellGrid::new();
+    let mut lines = 0;
+    for placement in placements {
+        let block = &mut blocks[placement.piece];
+        block.rotation_state = placement.rotation;
+        block.row_offset = 0;
+        block.column_offset = placement.column;
+        if grid.collides(block) {
+            grid = CellGrid::new();
+        }
+
+        grid.drop(block);
+        for position in block.get_cell_positions() {
+            grid.cells[position.row as usize][position.column as usize] =
+                placement.piece as c_int + 1;
+        }
+        lines += grid.clear_full_rows();
+    }
+
+    black_box(lines)
+}
@@ -0,0 +1,520 @@
+use std::{
+    fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use raylib::{
+    ffi::{GetFrameTime, GetKeyPressed, GetTime, IsKeyDown, IsKeyReleased},
+    prelude::KeyboardKey,
+};
+use tetris::{
+    block::Block,
+    clock::TICKS_PER_SECOND,
+    game::Game,
+    handling::Handling,
+    input::Input,
+    mode::Mode,
+    replay::{self, Playback, Replay},
+    rules::Rules,
+    save::{self, SavedGame},
+    settings::{self, Settings},
+    text::TextError,
+};
+
+use crate::{audio::Audio, renderer::Renderer};
+
+/// Where a game in progress is kept between launches.
+const SAVE_PATH: &str = "tetris.sav";
+
+/// The player's settings. A file with the defaults is written on the first
+/// launch, to be edited by hand.
+const SETTINGS_PATH: &str = "settings.txt";
+
+/// Where a replay of every finished or abandoned game is written.
+const REPLAY_DIRECTORY: &str = "replays";
+
+/// Playback speeds the replay viewer steps through, as multiples of real
+/// time.
+pub const PLAYBACK_SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// Index in [`PLAYBACK_SPEEDS`] of normal speed.
+const NORMAL_SPEED: usize = 2;
+
+/// Frames skipped by one seek in the replay viewer.
+const SEEK_FRAMES: u64 = 5 * TICKS_PER_SECOND as u64;
+
+/// Seconds counted down before play starts or resumes.
+const COUNTDOWN_SECONDS: f64 = 3.0;
+
+const KEY_BINDINGS: [(KeyboardKey, Input); 7] = [
+    (KeyboardKey::KEY_LEFT, Input::MoveLeft),
+    (KeyboardKey::KEY_RIGHT, Input::MoveRight),
+    (KeyboardKey::KEY_DOWN, Input::SoftDrop),
+    (KeyboardKey::KEY_SPACE, Input::HardDrop),
+    (KeyboardKey::KEY_UP, Input::Rotate),
+    (KeyboardKey::KEY_Z, Input::RotateCounterClockwise),
+    (KeyboardKey::KEY_C, Input::Hold),
+];
+
+/// What the window is currently showing. Each screen handles its own keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Screen {
+    Title,
+    ModeSelect {
+        selected: usize,
+    },
+    Countdown {
+        started_at: f64,
+    },
+    Playing,
+    /// `saved` is set once the game has been saved from this pause.
+    Paused {
+        saved: bool,
+    },
+    GameOver,
+    /// Watching a replay. `speed` indexes [`PLAYBACK_SPEEDS`].
+    Replay {
+        paused: bool,
+        speed: usize,
+    },
+}
+
+#[derive(Debug)]
+pub struct App {
+    screen: Screen,
+    game: Option<Game>,
+    /// The replay being watched on [`Screen::Replay`].
+    playback: Option<Playback>,
+    /// Frames of playback due but not yet stepped, carried between updates.
+    playback_progress: f64,
+    /// Index in [`Mode::ALL`] of the mode being played.
+    mode: usize,
+    /// The game that "Continue" resumes, if there is one.
+    saved: Option<SavedGame>,
+    /// Shown on the title screen, such as why a save could not be loaded.
+    message: Option<String>,
+    seed: Option<u64>,
+    /// How held keys repeat for this player.
+    handling: Handling,
+    /// Piece set loaded for each mode, in the order of [`Mode::ALL`].
+    piece_sets: Vec<Vec<Block>>,
+    renderer: Renderer,
+    audio: Audio,
+}
+
+impl App {
+    /// Creates the app on the title screen, with any game saved by the last
+    /// session ready to continue and the player's settings loaded. Every
+    /// game started uses `seed` if one is given, and a fresh random seed
+    /// otherwise.
+    pub fn new(seed: Option<u64>, piece_sets: Vec<Vec<Block>>) -> Self {
+        let (saved, mut message) = match save::read(SAVE_PATH) {
+            Ok(saved) => (Some(saved), None),
+            Err(TextError::Io(error)) if error.kind() == io::ErrorKind::NotFound => (None, None),
+            Err(error) => {
+                let message = format!("Save not loaded: {error}");
+                eprintln!("{SAVE_PATH}: {error}");
+                (None, Some(message))
+            }
+        };
+
+        let handling = match settings::read(SETTINGS_PATH) {
+            Ok(settings) => settings.handling,
+            Err(TextError::Io(error)) if error.kind() == io::ErrorKind::NotFound => {
+                if let Err(error) = settings::write(SETTINGS_PATH, &Settings::default()) {
+                    eprintln!("{SETTINGS_PATH}: {error}");
+                }
+                Handling::default()
+            }
+            Err(error) => {
+                eprintln!("{SETTINGS_PATH}: {error}");
+                message.get_or_insert(format!("Settings not loaded: {error}"));
+                Handling::default()
+            }
+        };
+
+        Self {
+            screen: Screen::Title,
+            game: None,
+            playback: None,
+            playback_progress: 0.0,
+            mode: 0,
+            saved,
+            message,
+            seed,
+            handling,
+            piece_sets,
+            renderer: Renderer::new(),
+            audio: Audio::new(),
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.audio.update();
+
+        loop {
+            let key_pressed = unsafe { GetKeyPressed() };
+            if key_pressed == 0 {
+                break;
+            }
+
+            self.handle_key(key_pressed);
+        }
+
+        match self.screen {
+            Screen::Countdown { started_at }
+                if unsafe { GetTime() } - started_at >= COUNTDOWN_SECONDS =>
+            {
+                self.release_keys_not_down();
+                self.game.as_mut().unwrap().resume();
+                self.screen = Screen::Playing;
+            }
+            Screen::Playing => {
+                let game = self.game.as_mut().unwrap();
+                for (key, input) in KEY_BINDINGS {
+                    if unsafe { IsKeyReleased(key as i32) } {
+                        game.release_input(input);
+                    }
+                }
+
+                game.update();
+                self.audio.play(&game.take_events());
+
+                if game.game_over {
+                    self.screen = Screen::GameOver;
+                    self.delete_save();
+                    self.write_replay();
+                }
+            }
+            Screen::Replay {
+                paused: false,
+                speed,
+            } => {
+                let playback = self.playback.as_mut().unwrap();
+                self.playback_progress += unsafe { GetFrameTime() } as f64
+                    * TICKS_PER_SECOND as f64
+                    * PLAYBACK_SPEEDS[speed];
+                while self.playback_progress >= 1.0 {
+                    self.playback_progress -= 1.0;
+                    playback.step();
+                }
+
+                // Sounds pile up when played back faster than recorded.
+                let events = playback.take_events();
+                if PLAYBACK_SPEEDS[speed] <= 1.0 {
+                    self.audio.play(&events);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn draw(&self) {
+        match self.screen {
+            Screen::Title => self
+                .renderer
+                .draw_title(self.saved.is_some(), self.message.as_deref()),
+            Screen::ModeSelect { selected } => self.renderer.draw_mode_select(selected),
+            Screen::Countdown { started_at } => {
+                let remaining = COUNTDOWN_SECONDS - (unsafe { GetTime() } - started_at);
+                self.renderer.draw(self.game.as_ref().unwrap());
+                self.renderer.draw_countdown(remaining.ceil() as i32);
+            }
+            Screen::Playing => self.renderer.draw(self.game.as_ref().unwrap()),
+            Screen::Paused { saved } => {
+                self.renderer.draw(self.game.as_ref().unwrap());
+                self.renderer.draw_paused(saved);
+            }
+            Screen::GameOver => {
+                self.renderer.draw(self.game.as_ref().unwrap());
+                self.renderer.draw_results(self.game.as_ref().unwrap());
+            }
+            Screen::Replay { paused, speed } => {
+                let playback = self.playback.as_ref().unwrap();
+                self.renderer.draw(playback.game());
+                self.renderer
+                    .draw_playback(playback, PLAYBACK_SPEEDS[speed], paused);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: i32) {
+        match self.screen {
+            Screen::Title => {
+                if key == KeyboardKey::KEY_ENTER as i32 {
+                    self.message = None;
+                    self.screen = Screen::ModeSelect { selected: 0 };
+                } else if key == KeyboardKey::KEY_C as i32 && self.saved.is_some() {
+                    self.continue_game();
+                } else if key == KeyboardKey::KEY_R as i32 {
+                    self.watch_last_replay();
+                }
+            }
+            Screen::ModeSelect { selected } => {
+                if key == KeyboardKey::KEY_UP as i32 {
+                    let selected = (selected + Mode::ALL.len() - 1) % Mode::ALL.len();
+                    self.screen = Screen::ModeSelect { selected };
+                } else if key == KeyboardKey::KEY_DOWN as i32 {
+                    let selected = (selected + 1) % Mode::ALL.len();
+                    self.screen = Screen::ModeSelect { selected };
+                } else if key == KeyboardKey::KEY_ENTER as i32 {
+                    self.start_game(selected);
+                } else if key == KeyboardKey::KEY_ESCAPE as i32 {
+                    self.screen = Screen::Title;
+                }
+            }
+            Screen::Countdown { .. } => {
+                if key == KeyboardKey::KEY_ESCAPE as i32 || key == KeyboardKey::KEY_P as i32 {
+                    self.pause();
+                }
+            }
+            Screen::Playing => {
+                if key == KeyboardKey::KEY_ESCAPE as i32 || key == KeyboardKey::KEY_P as i32 {
+                    self.pause();
+                } else if key == KeyboardKey::KEY_G as i32 {
+                    self.renderer.show_ghost = !self.renderer.show_ghost;
+                } else if let Some(&(_, input)) = KEY_BINDINGS
+                    .iter()
+                    .find(|(binding, _)| *binding as i32 == key)
+                {
+                    self.game.as_mut().unwrap().handle_input(input);
+                }
+            }
+            Screen::Paused { saved } => {
+                if key == KeyboardKey::KEY_ESCAPE as i32 || key == KeyboardKey::KEY_P as i32 {
+                    self.audio.resume_music();
+                    self.screen = Screen::Countdown {
+                        started_at: unsafe { GetTime() },
+                    };
+                } else if key == KeyboardKey::KEY_S as i32 && !saved {
+                    self.save_game();
+                    self.screen = Screen::Paused { saved: true };
+                } else if key == KeyboardKey::KEY_Q as i32 {
+                    self.audio.resume_music();
+                    self.write_replay();
+                    self.game = None;
+                    self.screen = Screen::Title;
+                }
+            }
+            Screen::GameOver => {
+                if key == KeyboardKey::KEY_ENTER as i32 {
+                    self.game = None;
+                    self.screen = Screen::Title;
+                }
+            }
+            Screen::Replay { paused, speed } => self.handle_replay_key(key, paused, speed),
+        }
+    }
+
+    fn handle_replay_key(&mut self, key: i32, paused: bool, speed: usize) {
+        let playback = self.playback.as_mut().unwrap();
+        let frame = playback.frame();
+        if key == KeyboardKey::KEY_SPACE as i32 || key == KeyboardKey::KEY_P as i32 {
+            self.playback_progress = 0.0;
+            self.screen = Screen::Replay {
+                paused: !paused,
+                speed,
+            };
+        } else if key == KeyboardKey::KEY_UP as i32 {
+            self.screen = Screen::Replay {
+                paused,
+                speed: (speed + 1).min(PLAYBACK_SPEEDS.len() - 1),
+            };
+        } else if key == KeyboardKey::KEY_DOWN as i32 {
+            self.screen = Screen::Replay {
+                paused,
+                speed: speed.saturating_sub(1),
+            };
+        } else if key == KeyboardKey::KEY_RIGHT as i32 {
+            playback.seek(frame + SEEK_FRAMES);
+        } else if key == KeyboardKey::KEY_LEFT as i32 {
+            playback.seek(frame.saturating_sub(SEEK_FRAMES));
+        } else if key == KeyboardKey::KEY_PERIOD as i32 && paused {
+            playback.seek(frame + 1);
+        } else if key == KeyboardKey::KEY_COMMA as i32 && paused {
+            playback.seek(frame.saturating_sub(1));
+        } else if key == KeyboardKey::KEY_HOME as i32 {
+            playback.seek(0);
+        } else if key == KeyboardKey::KEY_ESCAPE as i32 {
+            self.playback = None;
+            self.screen = Screen::Title;
+        }
+    }
+
+    /// Starts the mode at `index` in [`Mode::ALL`].
+    fn start_game(&mut self, index: usize) {
+        let seed = self.seed.unwrap_or_else(random_seed);
+        println!("Seed: {seed}");
+
+        let mut game = Game::new(seed, self.rules(index));
+        game.set_handling(self.handling);
+        self.play(index, game);
+    }
+
+    /// Resumes the saved game. The save is removed once it is back in play,
+    /// so it cannot be continued twice.
+    fn continue_game(&mut self) {
+        let saved = self.saved.take().unwrap();
+        let index = Mode::ALL
+            .iter()
+            .position(|&mode| mode == saved.mode)
+            .unwrap();
+
+        match Game::restore(self.rules(index), &saved.state) {
+            Ok(mut game) => {
+                // The game keeps the handling it was started with, even if
+                // the settings have changed since, so its replay matches.
+                game.set_handling(saved.handling);
+                self.delete_save();
+                self.play(index, game);
+            }
+            Err(error) => {
+                eprintln!("{SAVE_PATH}: {error}");
+                self.message = Some(format!("Save not loaded: {error}"));
+            }
+        }
+    }
+
+    fn play(&mut self, index: usize, mut game: Game) {
+        game.pause();
+        self.game = Some(game);
+        self.mode = index;
+        self.message = None;
+        self.screen = Screen::Countdown {
+            started_at: unsafe { GetTime() },
+        };
+    }
+
+    /// The rules of the mode at `index`, with the piece set loaded for it.
+    fn rules(&self, index: usize) -> Rules {
+        Rules {
+            pieces: self.piece_sets[index].clone(),
+            ..Mode::ALL[index].rules()
+        }
+    }
+
+    fn pause(&mut self) {
+        self.game.as_mut().unwrap().pause();
+        self.audio.pause_music();
+        self.screen = Screen::Paused { saved: false };
+    }
+
+    /// Writes the game in progress to the save file, to be continued from
+    /// the title screen of this or a later session.
+    pub fn save_game(&mut self) {
+        let Some(game) = self.game.as_ref().filter(|game| !game.game_over) else {
+            return;
+        };
+
+        let saved = SavedGame {
+            mode: Mode::ALL[self.mode],
+            handling: game.handling(),
+            state: game.state(),
+        };
+        match save::write(SAVE_PATH, &saved) {
+            Ok(()) => self.saved = Some(saved),
+            Err(error) => eprintln!("{SAVE_PATH}: {error}"),
+        }
+    }
+
+    fn delete_save(&mut self) {
+        self.saved = None;
+        if let Err(error) = fs::remove_file(SAVE_PATH) {
+            if error.kind() != io::ErrorKind::NotFound {
+                eprintln!("{SAVE_PATH}: {error}");
+            }
+        }
+    }
+
+    /// Writes a replay of the current game to [`REPLAY_DIRECTORY`], named
+    /// after the time it was written and the mode.
+    fn write_replay(&self) {
+        let game = self.game.as_ref().unwrap();
+        if game.input_log().is_empty() {
+            return;
+        }
+
+        let mode = Mode::ALL[self.mode];
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let path = Path::new(REPLAY_DIRECTORY)
+            .join(format!("{seconds}-{}.replay", mode.name().to_lowercase()));
+
+        let result = fs::create_dir_all(REPLAY_DIRECTORY)
+            .and_then(|()| replay::write(&path, &Replay::record(mode, game)));
+        match result {
+            Ok(()) => println!("Replay: {}", path.display()),
+            Err(error) => eprintln!("{}: {error}", path.display()),
+        }
+    }
+
+    /// Opens the replay viewer on `replay`, played with the piece set loaded
+    /// for its mode. Stays on the current screen with a message if that is
+    /// not the set the replay was recorded with.
+    pub fn watch_replay(&mut self, mut replay: Replay) {
+        let index = Mode::ALL
+            .iter()
+            .position(|&mode| mode == replay.mode)
+            .unwrap();
+        if let Err(error) = replay.set_pieces(self.piece_sets[index].clone()) {
+            eprintln!("{}: {error}", replay.mode.pieces_path());
+            self.message = Some(format!("Replay not played: {error}"));
+            return;
+        }
+
+        self.playback = Some(Playback::new(replay));
+        self.playback_progress = 0.0;
+        self.message = None;
+        self.screen = Screen::Replay {
+            paused: false,
+            speed: NORMAL_SPEED,
+        };
+    }
+
+    /// Watches the newest replay in [`REPLAY_DIRECTORY`]. Replay names start
+    /// with the time they were written, so the newest sorts last.
+    fn watch_last_replay(&mut self) {
+        let last = fs::read_dir(REPLAY_DIRECTORY)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "replay")
+            })
+            .max();
+        let Some(path) = last else {
+            self.message = Some("No replays recorded yet".to_string());
+            return;
+        };
+
+        match replay::read(&path) {
+            Ok(replay) => self.watch_replay(replay),
+            Err(error) => {
+                eprintln!("{}: {error}", path.display());
+                self.message = Some(format!("Replay not loaded: {error}"));
+            }
+        }
+    }
+
+    /// Releases inputs whose keys were let go while the game was not
+    /// listening, so they do not keep auto-repeating.
+    fn release_keys_not_down(&mut self) {
+        let game = self.game.as_mut().unwrap();
+        for (key, input) in KEY_BINDINGS {
+            if unsafe { !IsKeyDown(key as i32) } {
+                game.release_input(input);
+            }
+        }
+    }
+}
+
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default()
+}
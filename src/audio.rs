@@ -0,0 +1,77 @@
+use std::ffi::CString;
+
+use raylib::ffi::{
+    CloseAudioDevice, InitAudioDevice, LoadMusicStream, LoadSound, Music, PauseMusicStream,
+    PlayMusicStream, PlaySound, ResumeMusicStream, Sound, UnloadMusicStream, UnloadSound,
+    UpdateMusicStream,
+};
+use tetris::game::GameEvent;
+
+#[derive(Debug)]
+pub struct Audio {
+    music: Music,
+    rotate_sound: Sound,
+    clear_sound: Sound,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        let music_file_path = CString::new("assets/sounds/music.mp3").unwrap();
+        let rotate_file_path = CString::new("assets/sounds/rotate.mp3").unwrap();
+        let clear_file_path = CString::new("assets/sounds/clear.mp3").unwrap();
+
+        unsafe {
+            InitAudioDevice();
+        }
+
+        let audio = Self {
+            music: unsafe { LoadMusicStream(music_file_path.as_ptr()) },
+            rotate_sound: unsafe { LoadSound(rotate_file_path.as_ptr()) },
+            clear_sound: unsafe { LoadSound(clear_file_path.as_ptr()) },
+        };
+
+        unsafe {
+            PlayMusicStream(audio.music);
+        }
+
+        audio
+    }
+
+    pub fn update(&self) {
+        unsafe {
+            UpdateMusicStream(self.music);
+        }
+    }
+
+    pub fn pause_music(&self) {
+        unsafe {
+            PauseMusicStream(self.music);
+        }
+    }
+
+    pub fn resume_music(&self) {
+        unsafe {
+            ResumeMusicStream(self.music);
+        }
+    }
+
+    pub fn play(&self, events: &[GameEvent]) {
+        for event in events {
+            match event {
+                GameEvent::Rotated => unsafe { PlaySound(self.rotate_sound) },
+                GameEvent::RowsCleared(_) => unsafe { PlaySound(self.clear_sound) },
+            }
+        }
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        unsafe {
+            UnloadSound(self.rotate_sound);
+            UnloadSound(self.clear_sound);
+            UnloadMusicStream(self.music);
+            CloseAudioDevice();
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Plays replays without a window or audio and checks that each one ends
+//! where it was recorded to, for validating submitted runs and regression
+//! testing a corpus of replays.
+//!
+//! Usage: `verify-replay [--pieces FILE] FILE...`. Replays are played with
+//! the piece set built into the game for their mode, or with the piece file
+//! given by `--pieces`. Exits with 1 if a replay desyncs, 2 if one cannot be
+//! read and 3 if one was not played under its mode's rules. When several
+//! replays fail, the highest of these codes is returned.
+
+use std::{env, process};
+
+use tetris::{
+    clock::TICKS_PER_SECOND,
+    pieces,
+    replay::{self, ReplayResult},
+};
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let (pieces_path, paths) = match arguments.as_slice() {
+        [flag, path, paths @ ..] if flag == "--pieces" => (Some(path), paths),
+        paths => (None, paths),
+    };
+    if paths.is_empty() {
+        eprintln!("usage: verify-replay [--pieces FILE] FILE...");
+        process::exit(2);
+    }
+    let custom_pieces = pieces_path.map(|path| {
+        pieces::load(path).unwrap_or_else(|error| {
+            eprintln!("{path}: {error}");
+            process::exit(2);
+        })
+    });
+
+    let mut exit_code = 0;
+    for path in paths {
+        let mut replay = match replay::read(path) {
+            Ok(replay) => replay,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                exit_code = exit_code.max(2);
+                continue;
+            }
+        };
+
+        let pieces = custom_pieces
+            .clone()
+            .unwrap_or_else(|| replay.mode.rules().pieces);
+        if let Err(error) = replay.set_pieces(pieces) {
+            eprintln!("{path}: {error}");
+            exit_code = exit_code.max(2);
+            continue;
+        }
+
+        let game = replay.simulate();
+        let result = ReplayResult::new(&game);
+        println!("{path}");
+        println!("  mode   {} (seed {})", replay.mode.name(), replay.seed);
+        println!("  score  {}", result.score);
+        println!("  lines  {}", result.lines);
+        println!(
+            "  time   {} ({} frames)",
+            format_time(result.frames),
+            result.frames
+        );
+        println!("  hash   {:016x}", result.hash);
+
+        let rules = replay.check_rules();
+        if let Err(error) = &rules {
+            println!("  RULES: {error}");
+            exit_code = exit_code.max(3);
+        }
+        if result != replay.result {
+            let expected = &replay.result;
+            println!(
+                "  DESYNC: recorded score {}, lines {}, {} frames, hash {:016x}",
+                expected.score, expected.lines, expected.frames, expected.hash
+            );
+            exit_code = exit_code.max(1);
+        } else if rules.is_ok() {
+            println!("  ok");
+        }
+    }
+
+    process::exit(exit_code);
+}
+
+/// Formats a frame count as minutes, seconds and hundredths.
+fn format_time(frames: u64) -> String {
+    let hundredths = frames * 100 / TICKS_PER_SECOND as u64;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
@@ -1,54 +1,99 @@
-use std::{collections::HashMap, os::raw::c_int};
+use std::os::raw::c_int;
 
-use raylib::ffi::{Color, DrawRectangle};
-
-use crate::{colors::get_cell_colors, position::Position};
+use crate::{hasher::StateHasher, kicks::KickTable, position::Position};
 
 #[derive(Debug, Default, Clone)]
 pub struct Block {
     pub id: c_int,
-    pub cells: HashMap<c_int, Vec<Position>>,
-    cell_size: c_int,
+    pub name: String,
+    /// RGB colour of the block's cells.
+    pub color: [u8; 3],
+    /// Cells of each rotation state, relative to the block's box.
+    cells: Vec<Vec<Position>>,
+    /// The same states as row bitmasks, for collision checks.
+    masks: Vec<PieceMask>,
+    pub kick_table: KickTable,
+    /// Whether locking this block after a rotation can score a T-spin.
+    pub t_spin: bool,
     rotation_state: c_int,
-    colors: Vec<Color>,
     row_offset: c_int,
     column_offset: c_int,
+    spawn_row_offset: c_int,
+    spawn_column_offset: c_int,
 }
 
 impl Block {
     pub fn new() -> Self {
         Self {
-            cell_size: 30,
             rotation_state: 0,
-            colors: get_cell_colors(),
             row_offset: 0,
             column_offset: 0,
+            spawn_row_offset: 0,
+            spawn_column_offset: 0,
             ..Default::default()
         }
     }
 
-    pub fn draw(&mut self, offset_x: c_int, offset_y: c_int) {
-        let tiles = self.get_cell_positions();
-        for item in tiles {
-            unsafe {
-                DrawRectangle(
-                    item.column * self.cell_size + offset_x,
-                    item.row * self.cell_size + offset_y,
-                    self.cell_size - 1,
-                    self.cell_size - 1,
-                    self.colors[self.id as usize],
-                );
-            }
-        }
-    }
-
     pub fn r#move(&mut self, rows: c_int, columns: c_int) {
         self.row_offset += rows;
         self.column_offset += columns;
     }
 
+    /// Sets where the block enters the grid and moves it there.
+    pub fn set_spawn(&mut self, rows: c_int, columns: c_int) {
+        self.spawn_row_offset = rows;
+        self.spawn_column_offset = columns;
+        self.reset();
+    }
+
+    /// Moves the spawn offset, and the block with it.
+    pub fn shift_spawn(&mut self, rows: c_int, columns: c_int) {
+        self.set_spawn(
+            self.spawn_row_offset + rows,
+            self.spawn_column_offset + columns,
+        );
+    }
+
+    /// Puts the block in the given rotation state at the given offset.
+    /// Returns false, leaving the block as it was, if the block has no such
+    /// rotation state.
+    pub fn set_position(&mut self, rotation_state: c_int, row: c_int, column: c_int) -> bool {
+        if rotation_state < 0 || rotation_state >= self.rotation_count() {
+            return false;
+        }
+
+        self.rotation_state = rotation_state;
+        self.row_offset = row;
+        self.column_offset = column;
+        true
+    }
+
+    /// Returns the block to its spawn rotation and position.
+    pub fn reset(&mut self) {
+        self.rotation_state = 0;
+        self.row_offset = self.spawn_row_offset;
+        self.column_offset = self.spawn_column_offset;
+    }
+
+    /// Sets the cells of each rotation state, clockwise from the spawn state.
+    pub fn set_cells(&mut self, cells: Vec<Vec<Position>>) {
+        self.masks = cells.iter().map(|state| PieceMask::new(state)).collect();
+        self.cells = cells;
+        self.reset();
+    }
+
+    /// Number of distinct rotation states.
+    pub fn rotation_count(&self) -> c_int {
+        self.cells.len() as c_int
+    }
+
+    /// The current rotation state as row bitmasks.
+    pub fn mask(&self) -> &PieceMask {
+        &self.masks[self.rotation_state as usize]
+    }
+
     pub fn get_cell_positions(&self) -> Vec<Position> {
-        let tiles = &self.cells[&self.rotation_state];
+        let tiles = &self.cells[self.rotation_state as usize];
         let mut moved_tiles = Vec::new();
 
         for item in tiles {
@@ -60,6 +105,18 @@ impl Block {
         moved_tiles
     }
 
+    pub fn row_offset(&self) -> c_int {
+        self.row_offset
+    }
+
+    pub fn column_offset(&self) -> c_int {
+        self.column_offset
+    }
+
+    pub fn rotation_state(&self) -> c_int {
+        self.rotation_state
+    }
+
     pub fn rotate(&mut self) {
         self.rotation_state += 1;
 
@@ -68,6 +125,24 @@ impl Block {
         }
     }
 
+    /// Feeds `hasher` everything about the piece that affects play: id,
+    /// cells, kicks, spawn and T-spin flag.
+    pub(crate) fn hash(&self, hasher: &mut StateHasher) {
+        hasher.write(self.id as u64);
+        hasher.write(self.kick_table as u64);
+        hasher.write(self.t_spin as u64);
+        hasher.write(self.spawn_row_offset as u64);
+        hasher.write(self.spawn_column_offset as u64);
+        hasher.write(self.cells.len() as u64);
+        for state in &self.cells {
+            hasher.write(state.len() as u64);
+            for cell in state {
+                hasher.write(cell.row as u64);
+                hasher.write(cell.column as u64);
+            }
+        }
+    }
+
     pub fn undo_rotation(&mut self) {
         self.rotation_state -= 1;
 
@@ -77,375 +152,40 @@ impl Block {
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum BlockKind {
-    L(Block),
-    J(Block),
-    I(Block),
-    O(Block),
-    S(Block),
-    T(Block),
-    Z(Block),
+/// One rotation state of a block as a bitmask per row, bit `c` standing for
+/// column `c` of the block's box. Only the rows holding cells are kept.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PieceMask {
+    pub rows: Vec<u64>,
+    /// Box row of the first entry in `rows`.
+    pub top: c_int,
+    /// Leftmost and rightmost box columns holding a cell.
+    pub left: c_int,
+    pub right: c_int,
 }
 
-impl BlockKind {
-    pub fn get_all_blocks() -> Vec<BlockKind> {
-        vec![
-            Self::I(Self::iblock()),
-            Self::J(Self::jblock()),
-            Self::L(Self::lblock()),
-            Self::O(Self::oblock()),
-            Self::S(Self::sblock()),
-            Self::T(Self::tblock()),
-            Self::Z(Self::zblock()),
-        ]
-    }
-
-    pub fn lblock() -> Block {
-        let mut block = Block {
-            id: 1,
-            cells: {
-                let mut map = HashMap::new();
-                map.insert(
-                    0,
-                    vec![
-                        Position::new(0, 2),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                    ],
-                );
-
-                map.insert(
-                    1,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 1),
-                        Position::new(2, 1),
-                        Position::new(2, 2),
-                    ],
-                );
-
-                map.insert(
-                    2,
-                    vec![
-                        Position::new(0, 2),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                    ],
-                );
-
-                map.insert(
-                    3,
-                    vec![
-                        Position::new(0, 0),
-                        Position::new(0, 1),
-                        Position::new(1, 1),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map
-            },
-            ..Block::new()
-        };
-
-        block.r#move(0, 3);
-        block
-    }
-
-    pub fn jblock() -> Block {
-        let mut block = Block {
-            id: 2,
-            cells: {
-                let mut map = HashMap::new();
-                map.insert(
-                    0,
-                    vec![
-                        Position::new(0, 0),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                    ],
-                );
-
-                map.insert(
-                    1,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(0, 2),
-                        Position::new(1, 1),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map.insert(
-                    2,
-                    vec![
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                        Position::new(2, 2),
-                    ],
-                );
-
-                map.insert(
-                    3,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 1),
-                        Position::new(2, 0),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map
-            },
-            ..Block::new()
-        };
-
-        block.r#move(0, 3);
-        block
-    }
-
-    pub fn iblock() -> Block {
-        let mut block = Block {
-            id: 3,
-            cells: {
-                let mut map = HashMap::new();
-                map.insert(
-                    0,
-                    vec![
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                        Position::new(1, 3),
-                    ],
-                );
-
-                map.insert(
-                    1,
-                    vec![
-                        Position::new(0, 2),
-                        Position::new(1, 2),
-                        Position::new(2, 2),
-                        Position::new(3, 2),
-                    ],
-                );
-
-                map.insert(
-                    2,
-                    vec![
-                        Position::new(2, 0),
-                        Position::new(2, 1),
-                        Position::new(2, 2),
-                        Position::new(2, 3),
-                    ],
-                );
-
-                map.insert(
-                    3,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 1),
-                        Position::new(2, 1),
-                        Position::new(3, 1),
-                    ],
-                );
-
-                map
-            },
-            ..Block::new()
-        };
-
-        block.r#move(-1, 3);
-        block
-    }
-
-    pub fn oblock() -> Block {
-        let mut block = Block {
-            id: 4,
-            cells: {
-                let mut map = HashMap::new();
-                map.insert(
-                    0,
-                    vec![
-                        Position::new(0, 0),
-                        Position::new(0, 1),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                    ],
-                );
-
-                map
-            },
-            ..Block::new()
-        };
-
-        block.r#move(0, 4);
-        block
-    }
-
-    pub fn sblock() -> Block {
-        let mut block = Block {
-            id: 5,
-            cells: {
-                let mut map = HashMap::new();
-                map.insert(
-                    0,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(0, 2),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                    ],
-                );
-
-                map.insert(
-                    1,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                        Position::new(2, 2),
-                    ],
-                );
-
-                map.insert(
-                    2,
-                    vec![
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                        Position::new(2, 0),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map.insert(
-                    3,
-                    vec![
-                        Position::new(0, 0),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map
-            },
-            ..Block::new()
-        };
-
-        block.r#move(0, 3);
-        block
-    }
-
-    pub fn tblock() -> Block {
-        let mut block = Block {
-            id: 6,
-            cells: {
-                let mut map = HashMap::new();
-                map.insert(
-                    0,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                    ],
-                );
-
-                map.insert(
-                    1,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map.insert(
-                    2,
-                    vec![
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map.insert(
-                    3,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map
-            },
-            ..Block::new()
-        };
-
-        block.r#move(0, 3);
-        block
-    }
-
-    pub fn zblock() -> Block {
-        let mut block = Block {
-            id: 7,
-            cells: {
-                let mut map = HashMap::new();
-                map.insert(
-                    0,
-                    vec![
-                        Position::new(0, 0),
-                        Position::new(0, 1),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                    ],
-                );
-
-                map.insert(
-                    1,
-                    vec![
-                        Position::new(0, 2),
-                        Position::new(1, 1),
-                        Position::new(1, 2),
-                        Position::new(2, 1),
-                    ],
-                );
-
-                map.insert(
-                    2,
-                    vec![
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(2, 1),
-                        Position::new(2, 2),
-                    ],
-                );
-
-                map.insert(
-                    3,
-                    vec![
-                        Position::new(0, 1),
-                        Position::new(1, 0),
-                        Position::new(1, 1),
-                        Position::new(2, 0),
-                    ],
-                );
-
-                map
-            },
-            ..Block::new()
-        };
+impl PieceMask {
+    pub fn new(cells: &[Position]) -> Self {
+        let top = cells.iter().map(|cell| cell.row).min().unwrap_or_default();
+        let bottom = cells.iter().map(|cell| cell.row).max().unwrap_or_default();
+        let mut rows = vec![0; (bottom - top + 1) as usize];
+        for cell in cells {
+            rows[(cell.row - top) as usize] |= 1 << cell.column;
+        }
 
-        block.r#move(0, 3);
-        block
+        Self {
+            rows,
+            top,
+            left: cells
+                .iter()
+                .map(|cell| cell.column)
+                .min()
+                .unwrap_or_default(),
+            right: cells
+                .iter()
+                .map(|cell| cell.column)
+                .max()
+                .unwrap_or_default(),
+        }
     }
 }
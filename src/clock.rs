@@ -0,0 +1,150 @@
+use std::{cell::Cell, fmt::Debug, rc::Rc, time::Instant};
+
+/// Number of logic frames the simulation runs per second.
+pub const TICKS_PER_SECOND: u32 = 60;
+
+/// Duration of one logic frame, in seconds.
+pub const TICK_DURATION: f64 = 1.0 / TICKS_PER_SECOND as f64;
+
+/// Upper bound on the ticks a single update may run, so a long stall (a
+/// dragged window, a debugger break) does not fast-forward the game.
+const MAX_TICKS_PER_UPDATE: u32 = 10;
+
+/// Where the clock reads the current time from, in seconds.
+pub trait TimeSource: Debug {
+    fn now(&self) -> f64;
+}
+
+/// Wall-clock time measured from when the source was created.
+#[derive(Debug, Clone)]
+pub struct SystemTimeSource {
+    start: Instant,
+}
+
+impl SystemTimeSource {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+/// Time that only moves when told to. Clones share the same time, so a
+/// handle can be kept to advance a source that has been given to a clock.
+#[derive(Debug, Clone, Default)]
+pub struct ManualTimeSource {
+    now: Rc<Cell<f64>>,
+}
+
+impl ManualTimeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        self.now.set(self.now.get() + seconds);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+}
+
+/// Converts elapsed time from a [`TimeSource`] into whole logic ticks.
+#[derive(Debug)]
+pub struct Clock {
+    source: Box<dyn TimeSource>,
+    last_time: f64,
+    accumulator: f64,
+}
+
+impl Clock {
+    pub fn new(source: Box<dyn TimeSource>) -> Self {
+        let last_time = source.now();
+
+        Self {
+            source,
+            last_time,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Forgets any time elapsed since the previous call, so time spent
+    /// paused is not caught up on.
+    pub fn resync(&mut self) {
+        self.last_time = self.source.now();
+        self.accumulator = 0.0;
+    }
+
+    /// Returns how many ticks have elapsed since the previous call.
+    pub fn ticks_due(&mut self) -> u32 {
+        let now = self.source.now();
+        self.accumulator += now - self.last_time;
+        self.last_time = now;
+
+        let mut ticks = 0;
+        while self.accumulator >= TICK_DURATION {
+            self.accumulator -= TICK_DURATION;
+            ticks += 1;
+        }
+
+        if ticks > MAX_TICKS_PER_UPDATE {
+            ticks = MAX_TICKS_PER_UPDATE;
+        }
+
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock() -> (Clock, ManualTimeSource) {
+        let source = ManualTimeSource::new();
+        (Clock::new(Box::new(source.clone())), source)
+    }
+
+    #[test]
+    fn counts_whole_ticks() {
+        let (mut clock, source) = clock();
+        assert_eq!(clock.ticks_due(), 0);
+
+        source.advance(TICK_DURATION * 2.5);
+        assert_eq!(clock.ticks_due(), 2);
+
+        // The leftover half tick carries over to the next call.
+        source.advance(TICK_DURATION * 0.6);
+        assert_eq!(clock.ticks_due(), 1);
+    }
+
+    #[test]
+    fn caps_ticks_after_a_stall() {
+        let (mut clock, source) = clock();
+        source.advance(10.0);
+        assert_eq!(clock.ticks_due(), MAX_TICKS_PER_UPDATE);
+    }
+
+    #[test]
+    fn resync_forgets_elapsed_time() {
+        let (mut clock, source) = clock();
+        source.advance(TICK_DURATION * 5.5);
+        clock.resync();
+        source.advance(TICK_DURATION * 0.6);
+        assert_eq!(clock.ticks_due(), 0);
+    }
+}
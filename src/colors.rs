@@ -1,4 +1,7 @@
+use std::os::raw::c_int;
+
 use raylib::ffi::Color;
+use tetris::{block::Block, game::GARBAGE_ID};
 
 pub const DARK_GREY: Color = Color {
     r: 26,
@@ -7,52 +10,10 @@ pub const DARK_GREY: Color = Color {
     a: 255,
 };
 
-pub const GREEN: Color = Color {
-    r: 47,
-    g: 230,
-    b: 23,
-    a: 255,
-};
-
-pub const RED: Color = Color {
-    r: 232,
-    g: 18,
-    b: 18,
-    a: 255,
-};
-
-pub const ORANGE: Color = Color {
-    r: 226,
-    g: 116,
-    b: 17,
-    a: 255,
-};
-
-pub const YELLOW: Color = Color {
-    r: 237,
-    g: 234,
-    b: 4,
-    a: 255,
-};
-
-pub const PURPLE: Color = Color {
-    r: 116,
-    g: 0,
-    b: 247,
-    a: 255,
-};
-
-pub const CYAN: Color = Color {
-    r: 21,
-    g: 204,
-    b: 209,
-    a: 255,
-};
-
-pub const BLUE: Color = Color {
-    r: 13,
-    g: 64,
-    b: 216,
+pub const GREY: Color = Color {
+    r: 128,
+    g: 128,
+    b: 128,
     a: 255,
 };
 
@@ -70,6 +31,16 @@ pub const DARK_BLUE: Color = Color {
     a: 255,
 };
 
-pub fn get_cell_colors() -> Vec<Color> {
-    vec![DARK_GREY, GREEN, RED, ORANGE, YELLOW, PURPLE, CYAN, BLUE]
+pub fn block_color(block: &Block) -> Color {
+    let [r, g, b] = block.color;
+    Color { r, g, b, a: 255 }
+}
+
+/// Colour of a grid cell holding `value`, looked up in the game's pieces.
+pub fn cell_color(pieces: &[Block], value: c_int) -> Color {
+    match value {
+        0 => DARK_GREY,
+        GARBAGE_ID => GREY,
+        id => pieces.get(id as usize - 1).map_or(GREY, block_color),
+    }
 }
@@ -1,227 +1,1345 @@
-use std::{ffi::CString, os::raw::c_int};
-
-use raylib::{
-    ffi::{
-        rand, CloseAudioDevice, GetKeyPressed, InitAudioDevice, LoadMusicStream, LoadSound, Music,
-        PlayMusicStream, PlaySound, Sound, UnloadMusicStream, UnloadSound,
-    },
-    prelude::KeyboardKey,
-};
+use std::{collections::VecDeque, os::raw::c_int};
 
 use crate::{
-    block::{Block, BlockKind},
-    grid::Grid,
+    block::Block,
+    clock::{Clock, SystemTimeSource, TimeSource, TICKS_PER_SECOND, TICK_DURATION},
+    grid::{Grid, MAX_COLUMNS},
+    handling::Handling,
+    hasher::StateHasher,
+    input::{Input, InputEvent},
+    randomizer::Randomizer,
+    rules::{Rules, LINES_PER_LEVEL},
+    scoring::{LineClear, ScoreState, Spin},
+    statistics::Statistics,
 };
 
+/// Cell value used for garbage rows, apart from piece ids which count up
+/// from 1.
+pub const GARBAGE_ID: c_int = -1;
+
+/// Largest auto-repeat timer a saved game may hold. Real games stay far
+/// below it; anything above could overflow as the timer keeps counting.
+const MAX_TIMER: u32 = u32::MAX / 2;
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopOut {
+    /// A new piece spawned overlapping the stack.
+    BlockOut,
+    /// A piece locked entirely above the visible field.
+    LockOut,
+    /// A piece locked partly above the visible field, under rules that
+    /// treat this as a top out.
+    PartialLockOut,
+    /// Incoming garbage pushed the stack out of the top of the board.
+    GarbageOut,
+}
+
+impl TopOut {
+    pub fn description(&self) -> &'static str {
+        match self {
+            TopOut::BlockOut => "Block Out",
+            TopOut::LockOut => "Lock Out",
+            TopOut::PartialLockOut => "Partial Lock Out",
+            TopOut::GarbageOut => "Garbage Out",
+        }
+    }
+}
+
+/// Something that happened inside the simulation which a presentation layer
+/// (audio, effects) may want to react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+    Rotated,
+    RowsCleared(c_int),
+}
+
+/// Where a block is, as recorded in a [`GameState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockState {
+    /// Index of the block's piece in the rules' piece set.
+    pub piece: usize,
+    pub rotation: c_int,
+    pub row: c_int,
+    pub column: c_int,
+}
+
+impl BlockState {
+    fn new(block: &Block) -> Self {
+        Self {
+            piece: block.id as usize - 1,
+            rotation: block.rotation_state(),
+            row: block.row_offset(),
+            column: block.column_offset(),
+        }
+    }
+}
+
+/// Everything about a game that changes while it is played, enough to
+/// carry on from the same point under the same rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameState {
+    pub seed: u64,
+    pub frame: u64,
+    pub game_over: bool,
+    pub top_out: Option<TopOut>,
+    pub score: c_int,
+    pub level: c_int,
+    pub lines: c_int,
+    pub statistics: Statistics,
+    pub score_state: ScoreState,
+    pub held_inputs: Vec<Input>,
+    pub shift_timer: u32,
+    pub soft_drop_timer: u32,
+    pub gravity_progress: f64,
+    pub lock_timer: Option<u32>,
+    pub lock_resets: u32,
+    pub lowest_row: c_int,
+    /// Cell values row by row, hidden rows included.
+    pub cells: Vec<Vec<c_int>>,
+    pub randomizer: Vec<u64>,
+    pub current_block: BlockState,
+    /// The whole preview queue, including the block not shown yet.
+    pub next_blocks: Vec<BlockState>,
+    pub held_block: Option<BlockState>,
+    pub can_hold: bool,
+    pub last_rotation_kick: Option<usize>,
+    pub last_clear: Option<(u64, LineClear)>,
+    pub input_log: Vec<InputEvent>,
+}
+
 #[derive(Debug)]
 pub struct Game {
     pub game_over: bool,
+    top_out: Option<TopOut>,
+    paused: bool,
     pub score: c_int,
-    pub music: Music,
+    pub level: c_int,
+    pub lines: c_int,
+    pub statistics: Statistics,
+    score_state: ScoreState,
+    rules: Rules,
+    handling: Handling,
+    held_inputs: Vec<Input>,
+    shift_timer: u32,
+    soft_drop_timer: u32,
+    clock: Clock,
+    frame: u64,
+    gravity_progress: f64,
     grid: Grid,
-    blocks: Vec<BlockKind>,
+    seed: u64,
+    /// Every piece the randomizer can deal, in its index order.
+    pieces: Vec<Block>,
+    randomizer: Box<dyn Randomizer>,
     current_block: Option<Block>,
-    next_block: Option<Block>,
-    rotate_sound: Sound,
-    clear_sound: Sound,
+    next_blocks: VecDeque<Block>,
+    held_block: Option<Block>,
+    can_hold: bool,
+    lock_timer: Option<u32>,
+    lock_resets: u32,
+    lowest_row: c_int,
+    last_rotation_kick: Option<usize>,
+    last_clear: Option<(u64, LineClear)>,
+    /// Every input that reached the simulation, for replays.
+    input_log: Vec<InputEvent>,
+    events: Vec<GameEvent>,
 }
 
 impl Game {
-    pub fn new() -> Self {
-        let music_file_path = CString::new("assets/sounds/music.mp3").unwrap();
-        let rotate_file_path = CString::new("assets/sounds/rotate.mp3").unwrap();
-        let clear_file_path = CString::new("assets/sounds/clear.mp3").unwrap();
+    /// Creates a game whose piece sequence is fully determined by `seed`,
+    /// timed by the wall clock.
+    pub fn new(seed: u64, rules: Rules) -> Self {
+        Self::with_time_source(seed, rules, Box::new(SystemTimeSource::new()))
+    }
 
-        unsafe {
-            InitAudioDevice();
-        }
+    /// Creates a game that reads elapsed time from `time_source`.
+    pub fn with_time_source(seed: u64, mut rules: Rules, time_source: Box<dyn TimeSource>) -> Self {
+        rules.clamp();
+        let grid = Grid::new(rules.board_height, rules.board_width, rules.hidden_rows);
+        let pieces = rules.pieces.clone();
+        let randomizer = rules.randomizer.build(seed, pieces.len());
 
         let mut game = Self {
             game_over: false,
+            top_out: None,
+            paused: false,
             score: 0,
-            music: unsafe { LoadMusicStream(music_file_path.as_ptr()) },
-            grid: Grid::new(),
-            blocks: BlockKind::get_all_blocks(),
+            level: rules.start_level,
+            lines: 0,
+            statistics: Statistics::default(),
+            score_state: ScoreState::default(),
+            rules,
+            handling: Handling::default(),
+            held_inputs: Vec::new(),
+            shift_timer: 0,
+            soft_drop_timer: 0,
+            clock: Clock::new(time_source),
+            frame: 0,
+            gravity_progress: 0.0,
+            grid,
+            seed,
+            pieces,
+            randomizer,
             current_block: None,
-            next_block: None,
-            rotate_sound: unsafe { LoadSound(rotate_file_path.as_ptr()) },
-            clear_sound: unsafe { LoadSound(clear_file_path.as_ptr()) },
+            next_blocks: VecDeque::new(),
+            held_block: None,
+            can_hold: true,
+            lock_timer: None,
+            lock_resets: 0,
+            lowest_row: 0,
+            last_rotation_kick: None,
+            last_clear: None,
+            input_log: Vec::new(),
+            events: Vec::new(),
         };
 
-        unsafe {
-            PlayMusicStream(game.music);
+        game.fill_queue();
+        game.spawn_next_block();
+
+        game
+    }
+
+    /// Continues a game from `state`, timed by the wall clock. `rules` must
+    /// be the ones the game was started with.
+    pub fn restore(rules: Rules, state: &GameState) -> Result<Self, &'static str> {
+        Self::restore_with_time_source(rules, state, Box::new(SystemTimeSource::new()))
+    }
+
+    /// Continues a game from `state`, reading elapsed time from
+    /// `time_source`.
+    pub fn restore_with_time_source(
+        rules: Rules,
+        state: &GameState,
+        time_source: Box<dyn TimeSource>,
+    ) -> Result<Self, &'static str> {
+        let mut game = Self::with_time_source(state.seed, rules, time_source);
+
+        let num_cols = game.grid.num_cols as usize;
+        if state.cells.len() != game.grid.num_rows as usize
+            || state.cells.iter().any(|row| row.len() != num_cols)
+        {
+            return Err("the board is a different size");
+        }
+        game.grid.initialize();
+        for (row, values) in (0..).zip(&state.cells) {
+            for (column, &value) in (0..).zip(values) {
+                if value != 0 && value != GARBAGE_ID && value as usize > game.pieces.len() {
+                    return Err("the board holds a piece not in this mode's piece set");
+                }
+                game.grid.set_cell(row, column, value);
+            }
         }
 
-        game.current_block = Some(game.get_random_block());
-        game.next_block = Some(game.get_random_block());
+        if state.score < 0 || state.lines < 0 {
+            return Err("the score or lines are negative");
+        }
+        if state.level != game.rules.start_level + state.lines / LINES_PER_LEVEL {
+            return Err("the level does not match the lines cleared");
+        }
+        if !(0.0..1.0).contains(&state.gravity_progress) {
+            return Err("the gravity progress is out of range");
+        }
+        if state.score_state.combo.is_some_and(|combo| combo < 0) {
+            return Err("the combo is negative");
+        }
+        if state
+            .last_clear
+            .is_some_and(|(frame, _)| frame > state.frame)
+        {
+            return Err("the last clear is later than the current frame");
+        }
+        if state
+            .lock_timer
+            .is_some_and(|timer| timer > game.lock_delay_frames())
+            || state.shift_timer > MAX_TIMER
+            || state.soft_drop_timer > MAX_TIMER
+        {
+            return Err("a timer is out of range");
+        }
 
-        game
+        if !game.randomizer.restore(&state.randomizer) {
+            return Err("the randomizer state does not fit this mode");
+        }
+        if state.next_blocks.len() != game.next_blocks.len() {
+            return Err("the preview queue is a different length");
+        }
+        game.current_block = Some(game.restore_block(state.current_block)?);
+        game.next_blocks = state
+            .next_blocks
+            .iter()
+            .map(|&block| game.restore_block(block))
+            .collect::<Result<_, _>>()?;
+        game.held_block = state
+            .held_block
+            .map(|block| game.restore_block(block))
+            .transpose()?;
+        if !state.game_over && game.block_collides(game.current_block()) {
+            return Err("the current piece overlaps the stack");
+        }
+
+        game.frame = state.frame;
+        game.game_over = state.game_over;
+        game.top_out = state.top_out;
+        game.score = state.score;
+        game.level = state.level;
+        game.lines = state.lines;
+        game.statistics = state.statistics.clone();
+        game.score_state = state.score_state.clone();
+        game.held_inputs = state.held_inputs.clone();
+        game.shift_timer = state.shift_timer;
+        game.soft_drop_timer = state.soft_drop_timer;
+        game.gravity_progress = state.gravity_progress;
+        game.lock_timer = state.lock_timer;
+        game.lock_resets = state.lock_resets;
+        game.lowest_row = state.lowest_row;
+        game.can_hold = state.can_hold;
+        game.last_rotation_kick = state.last_rotation_kick;
+        game.last_clear = state.last_clear;
+        game.input_log = state.input_log.clone();
+
+        Ok(game)
     }
 
-    pub fn draw(&mut self) {
-        self.grid.draw();
-        self.current_block.as_mut().unwrap().draw(11, 11);
+    /// Captures everything needed to continue this game later.
+    pub fn state(&self) -> GameState {
+        GameState {
+            seed: self.seed,
+            frame: self.frame,
+            game_over: self.game_over,
+            top_out: self.top_out,
+            score: self.score,
+            level: self.level,
+            lines: self.lines,
+            statistics: self.statistics.clone(),
+            score_state: self.score_state.clone(),
+            held_inputs: self.held_inputs.clone(),
+            shift_timer: self.shift_timer,
+            soft_drop_timer: self.soft_drop_timer,
+            gravity_progress: self.gravity_progress,
+            lock_timer: self.lock_timer,
+            lock_resets: self.lock_resets,
+            lowest_row: self.lowest_row,
+            cells: (0..self.grid.num_rows)
+                .map(|row| {
+                    (0..self.grid.num_cols)
+                        .map(|column| self.grid.cell(row, column))
+                        .collect()
+                })
+                .collect(),
+            randomizer: self.randomizer.state(),
+            current_block: BlockState::new(self.current_block()),
+            next_blocks: self.next_blocks.iter().map(BlockState::new).collect(),
+            held_block: self.held_block.as_ref().map(BlockState::new),
+            can_hold: self.can_hold,
+            last_rotation_kick: self.last_rotation_kick,
+            last_clear: self.last_clear,
+            input_log: self.input_log.clone(),
+        }
+    }
 
-        if let Some(next_block) = &mut self.next_block {
-            match next_block.id {
-                3 => next_block.draw(255, 290),
-                4 => next_block.draw(255, 280),
-                _ => next_block.draw(270, 270),
-            };
+    /// The seed the current piece sequence was generated from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Every piece that can be dealt, indexed by id minus one.
+    pub fn pieces(&self) -> &[Block] {
+        &self.pieces
+    }
+
+    /// Number of logic frames simulated since the game started.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn current_block(&self) -> &Block {
+        self.current_block.as_ref().unwrap()
+    }
+
+    /// The upcoming blocks, soonest first, as many as the rules preview.
+    pub fn next_blocks(&self) -> impl Iterator<Item = &Block> {
+        self.next_blocks.iter().take(self.rules.preview_count)
+    }
+
+    pub fn held_block(&self) -> Option<&Block> {
+        self.held_block.as_ref()
+    }
+
+    /// The current block moved down to where a hard drop would land it.
+    pub fn ghost_block(&self) -> Block {
+        let mut ghost = self.current_block().clone();
+        ghost.r#move(self.drop_distance(&ghost), 0);
+
+        ghost
+    }
+
+    /// The most recent lock worth announcing, with the frame it happened on.
+    pub fn last_clear(&self) -> Option<(u64, LineClear)> {
+        self.last_clear
+    }
+
+    /// Returns the events produced since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// A fingerprint of every field of [`GameState`] apart from the input
+    /// log. Two runs of the same replay must produce the same hash on every
+    /// platform.
+    pub fn state_hash(&self) -> u64 {
+        // Destructured so a field added to `GameState` cannot be missed.
+        let GameState {
+            seed,
+            frame,
+            game_over,
+            top_out,
+            score,
+            level,
+            lines,
+            statistics,
+            score_state,
+            held_inputs,
+            shift_timer,
+            soft_drop_timer,
+            gravity_progress,
+            lock_timer,
+            lock_resets,
+            lowest_row,
+            cells,
+            randomizer,
+            current_block,
+            next_blocks,
+            held_block,
+            can_hold,
+            last_rotation_kick,
+            last_clear,
+            input_log: _,
+        } = self.state();
+        let mut hasher = StateHasher::default();
+
+        hasher.write(seed);
+        hasher.write(frame);
+        hasher.write(game_over as u64);
+        hasher.write_option(top_out.map(|top_out| top_out as u64));
+        hasher.write(score as u64);
+        hasher.write(level as u64);
+        hasher.write(lines as u64);
+        hasher.write(statistics.pieces as u64);
+        hasher.write(statistics.biggest_clears as u64);
+        hasher.write(statistics.t_spins as u64);
+        hasher.write(statistics.perfect_clears as u64);
+        hasher.write(statistics.max_combo as u64);
+        hasher.write(score_state.back_to_back as u64);
+        hasher.write_option(score_state.combo.map(|combo| combo as u64));
+        hasher.write(held_inputs.len() as u64);
+        for input in held_inputs {
+            hasher.write(input as u64);
+        }
+        hasher.write(shift_timer as u64);
+        hasher.write(soft_drop_timer as u64);
+        hasher.write(gravity_progress.to_bits());
+        hasher.write_option(lock_timer.map(u64::from));
+        hasher.write(lock_resets as u64);
+        hasher.write(lowest_row as u64);
+        hasher.write(cells.len() as u64);
+        for row in cells {
+            hasher.write(row.len() as u64);
+            for value in row {
+                hasher.write(value as u64);
+            }
+        }
+        hasher.write(randomizer.len() as u64);
+        for value in randomizer {
+            hasher.write(value);
         }
+        hasher.write(next_blocks.len() as u64);
+        for block in [Some(current_block), held_block]
+            .into_iter()
+            .chain(next_blocks.into_iter().map(Some))
+        {
+            hasher.write_option(block.map(|block| block.piece as u64));
+            if let Some(block) = block {
+                hasher.write(block.rotation as u64);
+                hasher.write(block.row as u64);
+                hasher.write(block.column as u64);
+            }
+        }
+        hasher.write(can_hold as u64);
+        hasher.write_option(last_rotation_kick.map(|kick| kick as u64));
+        hasher.write_option(last_clear.map(|(frame, _)| frame));
+        if let Some((_, clear)) = last_clear {
+            hasher.write(clear.lines as u64);
+            hasher.write(clear.spin as u64);
+            hasher.write(clear.perfect_clear as u64);
+        }
+
+        hasher.finish()
+    }
+
+    pub fn handling(&self) -> Handling {
+        self.handling
     }
 
-    pub fn handle_input(&mut self) {
-        let key_pressed = unsafe { GetKeyPressed() };
+    pub fn set_handling(&mut self, handling: Handling) {
+        self.handling = handling;
+    }
 
-        if self.game_over && key_pressed != 0 {
-            self.game_over = false;
-            self.reset();
+    /// Why the game ended, once it has.
+    pub fn top_out(&self) -> Option<TopOut> {
+        self.top_out
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stops gravity, lock delay and auto repeat until [`Game::resume`].
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.clock.resync();
+    }
+
+    /// Applies a freshly pressed input. Movement and soft drop keep
+    /// repeating on every tick until [`Game::release_input`] is called.
+    pub fn handle_input(&mut self, input: Input) {
+        // Inputs after the game ends are not logged, so they must not
+        // change the state a replay is checked against either.
+        if self.paused || self.game_over {
+            return;
         }
+        self.log_input(input, true);
 
-        match key_pressed {
-            key if key == KeyboardKey::KEY_LEFT as i32 => self.move_block_left(),
-            key if key == KeyboardKey::KEY_RIGHT as i32 => self.move_block_right(),
-            key if key == KeyboardKey::KEY_DOWN as i32 => {
-                self.move_block_down();
-                self.update_score(0, 1);
+        if matches!(input, Input::MoveLeft | Input::MoveRight | Input::SoftDrop) {
+            self.held_inputs.retain(|&held| held != input);
+            self.held_inputs.push(input);
+            match input {
+                Input::SoftDrop => self.soft_drop_timer = 0,
+                _ => self.shift_timer = 0,
+            }
+        }
+
+        match input {
+            Input::MoveLeft => {
+                self.move_block_left();
+            }
+            Input::MoveRight => {
+                self.move_block_right();
             }
-            key if key == KeyboardKey::KEY_UP as i32 => self.rotate_block(),
-            _ => (),
+            Input::SoftDrop => {
+                if self.move_block_down() {
+                    self.add_score(self.rules.scoring.soft_drop_points(1));
+                }
+            }
+            Input::Rotate => self.rotate_block(true),
+            Input::RotateCounterClockwise => self.rotate_block(false),
+            Input::HardDrop => self.hard_drop(),
+            Input::Hold => self.hold_block(),
         }
     }
 
-    pub fn move_block_down(&mut self) {
-        if !self.game_over {
-            self.current_block.as_mut().unwrap().r#move(1, 0);
-            if self.is_block_outside() || !self.block_fits() {
-                self.current_block.as_mut().unwrap().r#move(-1, 0);
+    pub fn release_input(&mut self, input: Input) {
+        if !self.game_over && self.held_inputs.contains(&input) {
+            self.log_input(input, false);
+            self.held_inputs.retain(|&held| held != input);
+        }
+    }
+
+    /// Inputs pressed and not yet released, oldest first.
+    pub fn held_inputs(&self) -> &[Input] {
+        &self.held_inputs
+    }
+
+    /// Every input press and release that affected the game so far, in
+    /// order. Replaying them on the same frames reproduces the game.
+    pub fn input_log(&self) -> &[InputEvent] {
+        &self.input_log
+    }
+
+    fn log_input(&mut self, input: Input, pressed: bool) {
+        self.input_log.push(InputEvent {
+            frame: self.frame,
+            input,
+            pressed,
+        });
+    }
+
+    /// Runs every logic frame that has become due on the game clock.
+    pub fn update(&mut self) {
+        if self.paused {
+            return;
+        }
+
+        for _ in 0..self.clock.ticks_due() {
+            self.tick();
+        }
+    }
+
+    /// Advances the simulation by exactly one logic frame.
+    pub fn tick(&mut self) {
+        if self.game_over {
+            return;
+        }
+
+        self.frame += 1;
+        self.repeat_held_inputs();
+        self.gravity_progress += TICK_DURATION / self.rules.gravity_at(self.level);
+        while self.gravity_progress >= 1.0 {
+            self.gravity_progress -= 1.0;
+            if !self.move_block_down() {
+                self.gravity_progress = 0.0;
+                break;
+            }
+        }
+
+        if self.is_block_on_ground() {
+            let lock_timer = self.lock_timer.get_or_insert(0);
+            *lock_timer += 1;
+            if *lock_timer >= self.lock_delay_frames()
+                || self.lock_resets >= self.rules.max_lock_resets
+            {
                 self.lock_block();
             }
+        } else {
+            self.lock_timer = None;
         }
     }
 
-    fn move_block_left(&mut self) {
-        if !self.game_over {
-            self.current_block.as_mut().unwrap().r#move(0, -1);
-            if self.is_block_outside() || !self.block_fits() {
-                self.current_block.as_mut().unwrap().r#move(0, 1);
+    /// Pushes `rows` rows of garbage up from the bottom of the board, each
+    /// with a gap at `hole_column`.
+    pub fn add_garbage(&mut self, rows: c_int, hole_column: c_int) {
+        if self.game_over || rows <= 0 {
+            return;
+        }
+
+        let rows = rows.min(self.grid.num_rows);
+        let pushed_out = (0..rows).any(|row| !self.grid.is_row_empty(row));
+        if pushed_out && self.rules.garbage_top_out {
+            self.end_game(TopOut::GarbageOut);
+            return;
+        }
+
+        self.grid.add_garbage(rows, hole_column, GARBAGE_ID);
+
+        for _ in 0..rows {
+            if !self.block_collides(self.current_block()) {
+                break;
             }
+            self.current_block.as_mut().unwrap().r#move(-1, 0);
+        }
+        if self.block_collides(self.current_block()) {
+            self.end_game(TopOut::GarbageOut);
         }
     }
 
-    fn move_block_right(&mut self) {
-        if !self.game_over {
-            self.current_block.as_mut().unwrap().r#move(0, 1);
-            if self.is_block_outside() || !self.block_fits() {
-                self.current_block.as_mut().unwrap().r#move(0, -1);
+    fn end_game(&mut self, top_out: TopOut) {
+        self.game_over = true;
+        self.top_out = Some(top_out);
+    }
+
+    /// Repeats the most recently held direction once DAS has charged, and
+    /// soft drop at its own rate.
+    fn repeat_held_inputs(&mut self) {
+        let shift = self
+            .held_inputs
+            .iter()
+            .rev()
+            .find(|&&held| held != Input::SoftDrop)
+            .copied();
+
+        if let Some(shift) = shift {
+            self.shift_timer += 1;
+            if self.shift_timer >= self.handling.das {
+                let repeats = self.shift_timer - self.handling.das;
+                if self.handling.arr == 0 {
+                    while self.shift_block(shift) {}
+                } else if repeats.is_multiple_of(self.handling.arr) {
+                    self.shift_block(shift);
+                }
+            }
+        }
+
+        if self.held_inputs.contains(&Input::SoftDrop) {
+            self.soft_drop_timer += 1;
+            if self.soft_drop_timer >= self.handling.soft_drop_interval.max(1) {
+                self.soft_drop_timer = 0;
+                if self.move_block_down() {
+                    self.add_score(self.rules.scoring.soft_drop_points(1));
+                }
             }
         }
     }
 
-    fn get_random_block(&mut self) -> Block {
-        if self.blocks.is_empty() {
-            self.blocks = BlockKind::get_all_blocks();
+    fn shift_block(&mut self, input: Input) -> bool {
+        match input {
+            Input::MoveLeft => self.move_block_left(),
+            _ => self.move_block_right(),
         }
+    }
 
-        let random_index = unsafe { rand() as usize % self.blocks.len() };
-        let block = self.blocks[random_index].clone();
-        self.blocks.remove(random_index);
+    /// Moves the current block down one row. Returns whether the block
+    /// moved; a block that cannot fall is left for the lock delay to lock.
+    fn move_block_down(&mut self) -> bool {
+        if self.game_over {
+            return false;
+        }
+
+        self.current_block.as_mut().unwrap().r#move(1, 0);
+        if self.block_collides(self.current_block()) {
+            self.current_block.as_mut().unwrap().r#move(-1, 0);
+            return false;
+        }
 
-        match block {
-            BlockKind::L(block)
-            | BlockKind::J(block)
-            | BlockKind::I(block)
-            | BlockKind::O(block)
-            | BlockKind::S(block)
-            | BlockKind::T(block)
-            | BlockKind::Z(block) => block,
+        self.last_rotation_kick = None;
+        let row = self.current_block().row_offset();
+        if row > self.lowest_row {
+            self.lowest_row = row;
+            self.lock_resets = 0;
         }
+
+        true
     }
 
-    fn is_block_outside(&self) -> bool {
-        let tiles = self.current_block.as_ref().unwrap().get_cell_positions();
+    fn is_block_on_ground(&self) -> bool {
+        let mut block = self.current_block().clone();
+        block.r#move(1, 0);
+
+        self.block_collides(&block)
+    }
+
+    fn lock_delay_frames(&self) -> u32 {
+        (self.rules.lock_delay * TICKS_PER_SECOND as f64).round() as u32
+    }
 
-        for item in tiles {
-            if self.grid.is_cell_outside(item.row, item.column) {
-                return true;
+    /// Restarts the lock delay after a successful move or rotation, as long
+    /// as the piece has resets left.
+    fn reset_lock_delay(&mut self) {
+        if self.lock_timer.is_some() && self.lock_resets < self.rules.max_lock_resets {
+            self.lock_timer = Some(0);
+            self.lock_resets += 1;
+        }
+    }
+
+    /// Drops the current block to its lowest legal row and locks it at once.
+    fn hard_drop(&mut self) {
+        if !self.game_over {
+            let distance = self.drop_distance(self.current_block());
+            if distance > 0 {
+                self.current_block.as_mut().unwrap().r#move(distance, 0);
+                self.last_rotation_kick = None;
             }
+            self.add_score(self.rules.scoring.hard_drop_points(distance));
+            self.lock_block();
         }
+    }
 
-        false
+    /// Number of rows `block` can fall before it lands.
+    fn drop_distance(&self, block: &Block) -> c_int {
+        self.grid
+            .drop_distance(block.mask(), block.row_offset(), block.column_offset())
     }
 
-    fn rotate_block(&mut self) {
-        if !self.game_over {
-            self.current_block.as_mut().unwrap().rotate();
-            if self.is_block_outside() || !self.block_fits() {
-                self.current_block.as_mut().unwrap().undo_rotation();
-            } else {
-                unsafe {
-                    PlaySound(self.rotate_sound);
-                }
+    /// Swaps the current block into the hold slot, at most once per piece.
+    fn hold_block(&mut self) {
+        if self.game_over || !self.can_hold {
+            return;
+        }
+
+        let mut block = self.current_block.take().unwrap();
+        block.reset();
+        match self.held_block.replace(block) {
+            Some(held_block) => self.enter_block(held_block),
+            None => self.spawn_next_block(),
+        }
+        self.can_hold = false;
+        self.gravity_progress = 0.0;
+    }
+
+    fn move_block_left(&mut self) -> bool {
+        if self.game_over {
+            return false;
+        }
+
+        self.current_block.as_mut().unwrap().r#move(0, -1);
+        if self.block_collides(self.current_block()) {
+            self.current_block.as_mut().unwrap().r#move(0, 1);
+            return false;
+        }
+
+        self.reset_lock_delay();
+        self.last_rotation_kick = None;
+        true
+    }
+
+    fn move_block_right(&mut self) -> bool {
+        if self.game_over {
+            return false;
+        }
+
+        self.current_block.as_mut().unwrap().r#move(0, 1);
+        if self.block_collides(self.current_block()) {
+            self.current_block.as_mut().unwrap().r#move(0, -1);
+            return false;
+        }
+
+        self.reset_lock_delay();
+        self.last_rotation_kick = None;
+        true
+    }
+
+    /// Whether `block` leaves the grid or overlaps a locked cell.
+    fn block_collides(&self, block: &Block) -> bool {
+        !self
+            .grid
+            .fits(block.mask(), block.row_offset(), block.column_offset())
+    }
+
+    /// Rotates the current block, trying each SRS wall kick in turn and
+    /// leaving the block untouched if none of them fit.
+    fn rotate_block(&mut self, clockwise: bool) {
+        if self.game_over {
+            return;
+        }
+
+        let block = self.current_block.as_mut().unwrap();
+        let kicks = block.kick_table.offsets(block.rotation_state(), clockwise);
+        if clockwise {
+            block.rotate();
+        } else {
+            block.undo_rotation();
+        }
+
+        for (kick, &(x, y)) in kicks.iter().enumerate() {
+            self.current_block.as_mut().unwrap().r#move(-y, x);
+            if !self.block_collides(self.current_block()) {
+                self.events.push(GameEvent::Rotated);
+                self.reset_lock_delay();
+                self.last_rotation_kick = Some(kick);
+                return;
             }
+            self.current_block.as_mut().unwrap().r#move(y, -x);
+        }
+
+        let block = self.current_block.as_mut().unwrap();
+        if clockwise {
+            block.undo_rotation();
+        } else {
+            block.rotate();
+        }
+    }
+
+    /// Classifies the lock of the current block with the 3-corner rule: a T
+    /// whose last move was a rotation and which has three of the four
+    /// corners around its centre occupied. Both corners on the side it
+    /// points to make a full T-spin, as does the last SRS kick.
+    fn detect_spin(&self) -> Spin {
+        let block = self.current_block();
+        let Some(kick) = self.last_rotation_kick else {
+            return Spin::None;
+        };
+        if !block.t_spin {
+            return Spin::None;
+        }
+
+        let corner_occupied = |row: c_int, column: c_int| {
+            let row = block.row_offset() + row;
+            let column = block.column_offset() + column;
+            self.grid.is_cell_outside(row, column) || !self.grid.is_cell_empty(row, column)
+        };
+
+        let corners = [(0, 0), (0, 2), (2, 2), (2, 0)];
+        let occupied = corners
+            .iter()
+            .filter(|&&(row, column)| corner_occupied(row, column))
+            .count();
+        if occupied < 3 {
+            return Spin::None;
+        }
+
+        let front = match block.rotation_state() {
+            0 => [corners[0], corners[1]],
+            1 => [corners[1], corners[2]],
+            2 => [corners[2], corners[3]],
+            _ => [corners[3], corners[0]],
+        };
+        if front
+            .iter()
+            .all(|&(row, column)| corner_occupied(row, column))
+            || kick == 4
+        {
+            Spin::Full
+        } else {
+            Spin::Mini
         }
     }
 
     fn lock_block(&mut self) {
-        let tiles = self.current_block.as_ref().unwrap().get_cell_positions();
-        for item in tiles {
-            self.grid.grid[item.row as usize][item.column as usize] =
-                self.current_block.as_ref().unwrap().id;
+        let spin = self.detect_spin();
+        let block = self.current_block.as_ref().unwrap();
+        let tiles = block.get_cell_positions();
+        let hidden_cells = tiles
+            .iter()
+            .filter(|item| self.grid.is_cell_hidden(item.row))
+            .count();
+        self.grid.place(
+            block.mask(),
+            block.row_offset(),
+            block.column_offset(),
+            block.id,
+        );
+
+        if hidden_cells == tiles.len() {
+            self.end_game(TopOut::LockOut);
+            return;
         }
-        self.current_block = self.next_block.clone();
-        if !self.block_fits() {
-            self.game_over = true;
+        if hidden_cells > 0 && self.rules.partial_lock_out {
+            self.end_game(TopOut::PartialLockOut);
+            return;
         }
-        self.next_block = Some(self.get_random_block());
+
         let rows_cleared = self.grid.clear_full_rows();
+        let clear = LineClear {
+            lines: rows_cleared,
+            spin,
+            perfect_clear: rows_cleared > 0 && self.grid.is_empty(),
+        };
+        let points = self
+            .rules
+            .scoring
+            .score_lock(&mut self.score_state, clear, self.level);
+        self.add_score(points);
+        self.statistics.record_lock(
+            clear,
+            self.score_state.combo,
+            self.rules.scoring.biggest_clear_lines(),
+        );
+        if self.rules.scoring.label(clear).is_some() {
+            self.last_clear = Some((self.frame, clear));
+        }
         if rows_cleared > 0 {
-            unsafe {
-                PlaySound(self.clear_sound);
-            }
-            self.update_score(rows_cleared, 0);
+            self.events.push(GameEvent::RowsCleared(rows_cleared));
+            self.lines = self.lines.saturating_add(rows_cleared);
+            self.level = self.rules.start_level + self.lines / LINES_PER_LEVEL;
         }
+
+        self.spawn_next_block();
+        self.can_hold = true;
     }
 
-    fn block_fits(&mut self) -> bool {
-        let tiles = self.current_block.as_ref().unwrap().get_cell_positions();
-        for item in tiles {
-            if !self.grid.is_cell_empty(item.row, item.column) {
-                return false;
+    /// Adds to the score, which stops at `c_int::MAX` rather than
+    /// overflowing.
+    fn add_score(&mut self, points: c_int) {
+        self.score = self.score.saturating_add(points);
+    }
+
+    fn spawn_next_block(&mut self) {
+        let block = self.next_blocks.pop_front().unwrap();
+        let next_block = self.deal_block();
+        self.next_blocks.push_back(next_block);
+        self.enter_block(block);
+    }
+
+    /// Makes `block` the current block at its spawn position, then drops it
+    /// one row towards the visible field if there is room.
+    fn enter_block(&mut self, block: Block) {
+        self.current_block = Some(block);
+        if self.block_collides(self.current_block()) {
+            self.end_game(TopOut::BlockOut);
+        } else {
+            self.current_block.as_mut().unwrap().r#move(1, 0);
+            if self.block_collides(self.current_block()) {
+                self.current_block.as_mut().unwrap().r#move(-1, 0);
             }
         }
+        self.reset_lock_state();
+    }
 
-        true
+    /// Takes the next block from the randomizer.
+    fn deal_block(&mut self) -> Block {
+        let piece = self.randomizer.next_piece();
+        self.piece_block(piece)
     }
 
-    fn reset(&mut self) {
-        self.grid.initialize();
-        self.blocks = BlockKind::get_all_blocks();
-        self.current_block = Some(self.get_random_block());
-        self.next_block = Some(self.get_random_block());
-        self.score = 0;
+    /// A block of the piece at index `piece`, with its spawn moved into the
+    /// hidden rows and centred on this board. Block definitions spawn for a
+    /// 10-wide board without hidden rows.
+    fn piece_block(&self, piece: usize) -> Block {
+        let mut block = self.pieces[piece].clone();
+        block.shift_spawn(self.grid.hidden_rows - 2, (self.grid.num_cols - 10) / 2);
+
+        block
     }
 
-    fn update_score(&mut self, lines_cleared: c_int, move_down_points: c_int) {
-        match lines_cleared {
-            1 => self.score += 100,
-            2 => self.score += 300,
-            3 => self.score += 500,
-            _ => (),
+    /// Rebuilds a block from a saved game.
+    fn restore_block(&self, state: BlockState) -> Result<Block, &'static str> {
+        if state.piece >= self.pieces.len() {
+            return Err("a piece is not in this mode's piece set");
         }
 
-        self.score += move_down_points;
+        // Anywhere a piece box can still overlap the board, so the grid
+        // checks never see out of range offsets.
+        let rows = -MAX_COLUMNS..=self.grid.num_rows;
+        let columns = -MAX_COLUMNS..=self.grid.num_cols;
+        if !rows.contains(&state.row) || !columns.contains(&state.column) {
+            return Err("a piece is outside the board");
+        }
+
+        let mut block = self.piece_block(state.piece);
+        if !block.set_position(state.rotation, state.row, state.column) {
+            return Err("a piece is in a rotation it does not have");
+        }
+
+        Ok(block)
+    }
+
+    fn reset_lock_state(&mut self) {
+        self.last_rotation_kick = None;
+        self.lock_timer = None;
+        self.lock_resets = 0;
+        self.lowest_row = self.current_block().row_offset();
+    }
+
+    /// Deals a fresh preview queue, one block longer than is shown so the
+    /// current block can be spawned from it.
+    fn fill_queue(&mut self) {
+        self.next_blocks.clear();
+        for _ in 0..=self.rules.preview_count.max(1) {
+            let block = self.deal_block();
+            self.next_blocks.push_back(block);
+        }
     }
 }
 
-impl Drop for Game {
-    fn drop(&mut self) {
-        unsafe {
-            UnloadSound(self.rotate_sound);
-            UnloadSound(self.clear_sound);
-            UnloadMusicStream(self.music);
-            CloseAudioDevice();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualTimeSource, randomizer::RandomizerKind};
+
+    // Indices into the standard piece set.
+    const I: usize = 0;
+    const J: usize = 1;
+    const O: usize = 3;
+    const T: usize = 5;
+
+    /// Rows of the default board, hidden ones included.
+    const BOTTOM: c_int = 39;
+
+    /// A default game dealing `pieces` in order, repeating.
+    fn game(pieces: &[usize]) -> Game {
+        game_with(pieces, Rules::default())
+    }
+
+    fn game_with(pieces: &[usize], rules: Rules) -> Game {
+        let rules = Rules {
+            randomizer: RandomizerKind::Sequence(pieces.to_vec()),
+            ..rules
+        };
+        Game::with_time_source(0, rules, Box::new(ManualTimeSource::new()))
+    }
+
+    fn fill(game: &mut Game, row: c_int, columns: impl IntoIterator<Item = c_int>) {
+        for column in columns {
+            game.grid.set_cell(row, column, GARBAGE_ID);
+        }
+    }
+
+    /// Soft drops the current block until it rests on the stack.
+    fn land(game: &mut Game) {
+        while game.drop_distance(game.current_block()) > 0 {
+            game.handle_input(Input::SoftDrop);
+        }
+        game.release_input(Input::SoftDrop);
+    }
+
+    fn ticks(game: &mut Game, count: usize) {
+        for _ in 0..count {
+            game.tick();
+        }
+    }
+
+    #[test]
+    fn hard_drop_scores_two_points_per_row() {
+        let mut game = game(&[I]);
+        let row = game.current_block().row_offset();
+        game.handle_input(Input::HardDrop);
+
+        // The I sits in the second row of its box.
+        assert_eq!(game.score, 2 * (BOTTOM - row - 1));
+        assert!((3..7).all(|column| game.grid().cell(BOTTOM, column) == 1));
+        assert_eq!(game.statistics.pieces, 1);
+    }
+
+    #[test]
+    fn hold_once_per_piece() {
+        let mut game = game(&[I, O, T]);
+        game.handle_input(Input::Hold);
+        assert_eq!(game.held_block().unwrap().id, I as c_int + 1);
+        assert_eq!(game.current_block().id, O as c_int + 1);
+
+        game.handle_input(Input::Hold);
+        assert_eq!(game.held_block().unwrap().id, I as c_int + 1);
+        assert_eq!(game.current_block().id, O as c_int + 1);
+
+        game.handle_input(Input::HardDrop);
+        game.handle_input(Input::Hold);
+        assert_eq!(game.held_block().unwrap().id, T as c_int + 1);
+        assert_eq!(game.current_block().id, I as c_int + 1);
+    }
+
+    #[test]
+    fn locks_after_thirty_frames_on_the_ground() {
+        let mut game = game(&[O]);
+        land(&mut game);
+
+        ticks(&mut game, 29);
+        assert!(game.grid().is_empty());
+        game.tick();
+        assert!(!game.grid().is_empty());
+    }
+
+    #[test]
+    fn moves_reset_the_lock_delay_fifteen_times() {
+        let mut game = game(&[O]);
+        land(&mut game);
+
+        for step in 0..15 {
+            ticks(&mut game, 20);
+            let input = if step % 2 == 0 {
+                Input::MoveLeft
+            } else {
+                Input::MoveRight
+            };
+            game.handle_input(input);
+            game.release_input(input);
+        }
+        assert!(game.grid().is_empty());
+
+        // Out of resets, the piece locks as soon as it touches down.
+        game.tick();
+        assert!(!game.grid().is_empty());
+    }
+
+    /// A T resting at the bottom in `rotation`, with its box at `row` and
+    /// column 0, as if it had just rotated in with `kick`.
+    fn place_t(game: &mut Game, rotation: c_int, row: c_int, kick: usize) {
+        let block = game.current_block.as_mut().unwrap();
+        assert!(block.set_position(rotation, row, 0));
+        game.last_rotation_kick = Some(kick);
+    }
+
+    #[test]
+    fn t_spin_double_with_both_front_corners() {
+        let mut game = game(&[T]);
+        // The T points down into a slot with an overhang over its left
+        // corner.
+        fill(&mut game, BOTTOM - 2, [0]);
+        fill(&mut game, BOTTOM - 1, 3..10);
+        fill(&mut game, BOTTOM, [0].into_iter().chain(2..10));
+        place_t(&mut game, 2, BOTTOM - 2, 0);
+        game.handle_input(Input::HardDrop);
+
+        let (_, clear) = game.last_clear().unwrap();
+        assert_eq!((clear.lines, clear.spin), (2, Spin::Full));
+        assert_eq!(game.score, 1200);
+        assert_eq!(game.statistics.t_spins, 1);
+    }
+
+    #[test]
+    fn t_spin_mini_without_both_front_corners() {
+        let mut game = game(&[T]);
+        // Pointing up on the floor, the two corners below are the wall.
+        fill(&mut game, BOTTOM - 1, [0]);
+        fill(&mut game, BOTTOM, 3..10);
+        place_t(&mut game, 0, BOTTOM - 1, 0);
+        game.handle_input(Input::HardDrop);
+
+        let (_, clear) = game.last_clear().unwrap();
+        assert_eq!((clear.lines, clear.spin), (1, Spin::Mini));
+    }
+
+    #[test]
+    fn last_kick_makes_a_mini_full() {
+        let mut game = game(&[T]);
+        fill(&mut game, BOTTOM - 1, [0]);
+        fill(&mut game, BOTTOM, 3..10);
+        place_t(&mut game, 0, BOTTOM - 1, 4);
+        game.handle_input(Input::HardDrop);
+
+        let (_, clear) = game.last_clear().unwrap();
+        assert_eq!((clear.lines, clear.spin), (1, Spin::Full));
+    }
+
+    #[test]
+    fn no_spin_with_two_corners() {
+        let mut game = game(&[T]);
+        fill(&mut game, BOTTOM - 1, 3..10);
+        fill(&mut game, BOTTOM, [0].into_iter().chain(2..10));
+        place_t(&mut game, 2, BOTTOM - 2, 0);
+        game.handle_input(Input::HardDrop);
+
+        assert_eq!(game.last_clear().unwrap().1.spin, Spin::None);
+        assert_eq!(game.lines, 2);
+    }
+
+    #[test]
+    fn block_out_when_the_next_piece_cannot_spawn() {
+        let mut game = game(&[O]);
+        for _ in 0..4 {
+            game.handle_input(Input::MoveLeft);
+        }
+        let spawn_row = game.grid().hidden_rows - 2;
+        fill(&mut game, spawn_row, [4]);
+        game.handle_input(Input::HardDrop);
+
+        assert!(game.game_over);
+        assert_eq!(game.top_out(), Some(TopOut::BlockOut));
+    }
+
+    #[test]
+    fn lock_out_when_a_piece_locks_above_the_field() {
+        let mut game = game(&[I]);
+        let visible = game.grid().hidden_rows;
+        for row in visible..=BOTTOM {
+            fill(&mut game, row, [5]);
         }
+        game.handle_input(Input::HardDrop);
+
+        assert_eq!(game.top_out(), Some(TopOut::LockOut));
+    }
+
+    #[test]
+    fn partial_lock_out_only_under_its_rule() {
+        for partial_lock_out in [false, true] {
+            let rules = Rules {
+                partial_lock_out,
+                ..Rules::default()
+            };
+            let mut game = game_with(&[J], rules);
+            // The J lies across the bottom hidden row and the top visible
+            // one, out of the way of the next spawn.
+            for _ in 0..3 {
+                game.handle_input(Input::MoveLeft);
+            }
+            let visible = game.grid().hidden_rows;
+            for row in visible + 1..=BOTTOM {
+                fill(&mut game, row, [1]);
+            }
+            game.handle_input(Input::HardDrop);
+
+            let expected = partial_lock_out.then_some(TopOut::PartialLockOut);
+            assert_eq!(game.top_out(), expected);
+        }
+    }
+
+    #[test]
+    fn garbage_out_when_the_stack_is_pushed_off_the_board() {
+        let mut game = game(&[O]);
+        fill(&mut game, 0, [0]);
+        game.add_garbage(1, 9);
+
+        assert_eq!(game.top_out(), Some(TopOut::GarbageOut));
+    }
+
+    #[test]
+    fn level_goes_up_every_ten_lines() {
+        let mut game = game(&[I]);
+        for line in 1..=20 {
+            fill(&mut game, BOTTOM, (0..3).chain(7..10));
+            game.handle_input(Input::HardDrop);
+            assert_eq!(game.lines, line);
+            assert_eq!(game.level, 1 + line / LINES_PER_LEVEL);
+        }
+    }
+
+    #[test]
+    fn perfect_clear_only_when_the_board_empties() {
+        let mut game = game(&[I]);
+        fill(&mut game, BOTTOM, (0..3).chain(7..10));
+        game.handle_input(Input::HardDrop);
+        assert!(game.last_clear().unwrap().1.perfect_clear);
+        assert_eq!(game.statistics.perfect_clears, 1);
+
+        fill(&mut game, BOTTOM - 1, [0]);
+        fill(&mut game, BOTTOM, (0..3).chain(7..10));
+        game.handle_input(Input::HardDrop);
+        assert!(!game.grid().is_empty());
+        assert_eq!(game.statistics.perfect_clears, 1);
+        assert_eq!(game.lines, 2);
+    }
+
+    #[test]
+    fn restore_rejects_corrupted_state() {
+        let mut game = game(&[I]);
+        fill(&mut game, BOTTOM, (0..3).chain(7..10));
+        game.handle_input(Input::HardDrop);
+        let state = game.state();
+        assert!(Game::restore(Rules::default(), &state).is_ok());
+
+        let corruptions: [fn(&mut GameState); 4] = [
+            |state| state.level = c_int::MAX,
+            |state| state.lines = 10,
+            |state| state.gravity_progress = f64::NAN,
+            |state| state.gravity_progress = 1.0,
+        ];
+        for corrupt in corruptions {
+            let mut corrupted = state.clone();
+            corrupt(&mut corrupted);
+            assert!(Game::restore(Rules::default(), &corrupted).is_err());
+        }
+    }
+
+    #[test]
+    fn score_stops_at_the_maximum() {
+        let mut game = game(&[I]);
+        game.score = c_int::MAX - 1;
+        game.level = c_int::MAX;
+        fill(&mut game, BOTTOM, (0..3).chain(7..10));
+        game.handle_input(Input::HardDrop);
+        assert_eq!(game.score, c_int::MAX);
+    }
+
+    #[test]
+    fn held_direction_repeats_after_das_at_arr() {
+        let mut game = game(&[O]);
+        let start = game.current_block().column_offset();
+        game.handle_input(Input::MoveRight);
+        assert_eq!(game.current_block().column_offset(), start + 1);
+
+        // DAS is 10 frames, then one shift every 2 frames.
+        ticks(&mut game, 9);
+        assert_eq!(game.current_block().column_offset(), start + 1);
+        game.tick();
+        assert_eq!(game.current_block().column_offset(), start + 2);
+        game.tick();
+        assert_eq!(game.current_block().column_offset(), start + 2);
+        game.tick();
+        assert_eq!(game.current_block().column_offset(), start + 3);
+
+        game.release_input(Input::MoveRight);
+        ticks(&mut game, 10);
+        assert_eq!(game.current_block().column_offset(), start + 3);
+    }
+
+    #[test]
+    fn zero_arr_shifts_to_the_wall() {
+        let mut game = game(&[O]);
+        game.set_handling(Handling {
+            arr: 0,
+            ..Handling::default()
+        });
+        game.handle_input(Input::MoveLeft);
+        ticks(&mut game, 10);
+        assert_eq!(game.current_block().column_offset(), 0);
     }
 }
@@ -1,65 +1,87 @@
 use std::os::raw::c_int;
 
-use raylib::ffi::{Color, DrawRectangle};
+use crate::block::PieceMask;
 
-use crate::colors::get_cell_colors;
+/// The widest board a row bitmask can hold.
+pub const MAX_COLUMNS: c_int = u64::BITS as c_int;
 
-#[derive(Debug, Default)]
+/// The playfield. Row 0 is the top of a buffer of `hidden_rows` rows that
+/// sit above the visible field; pieces spawn there.
+///
+/// Occupancy is kept as one bitmask per row so pieces can be tested and
+/// placed a row at a time, with the value of each cell stored separately.
+#[derive(Debug, Default, Clone)]
 pub struct Grid {
-    pub grid: [[c_int; 10]; 20],
-    num_rows: c_int,
-    num_cols: c_int,
-    cell_size: c_int,
-    colors: Vec<Color>,
+    /// Bit `c` of a row is set when column `c` is filled.
+    rows: Vec<u64>,
+    /// Cell values row by row: 0 when empty, otherwise what filled it.
+    cells: Vec<c_int>,
+    /// Total rows, hidden ones included.
+    pub num_rows: c_int,
+    pub num_cols: c_int,
+    pub hidden_rows: c_int,
 }
 
 impl Grid {
-    pub fn new() -> Self {
+    /// Creates an empty grid. `num_cols` is at most [`MAX_COLUMNS`].
+    pub fn new(visible_rows: c_int, num_cols: c_int, hidden_rows: c_int) -> Self {
+        assert!(
+            num_cols <= MAX_COLUMNS,
+            "grid is wider than {MAX_COLUMNS} columns"
+        );
+
         let mut grid = Grid {
-            num_rows: 20,
-            num_cols: 10,
-            cell_size: 30,
+            num_rows: visible_rows + hidden_rows,
+            num_cols,
+            hidden_rows,
             ..Default::default()
         };
 
         grid.initialize();
-        grid.colors = get_cell_colors();
 
         grid
     }
 
+    /// Empties every cell, reusing the existing storage.
     pub fn initialize(&mut self) {
-        for row in 0..self.num_rows as usize {
-            for colomn in 0..self.num_cols as usize {
-                self.grid[row][colomn] = 0;
-            }
-        }
+        self.rows.clear();
+        self.rows.resize(self.num_rows as usize, 0);
+        self.cells.clear();
+        self.cells
+            .resize((self.num_rows * self.num_cols) as usize, 0);
+    }
+
+    pub fn visible_rows(&self) -> c_int {
+        self.num_rows - self.hidden_rows
+    }
+
+    pub fn is_cell_hidden(&self, row: c_int) -> bool {
+        row < self.hidden_rows
     }
 
     #[allow(unused)]
     pub fn print(&self) {
-        for row in 0..self.num_rows as usize {
-            for column in 0..self.num_cols as usize {
-                print!("{} ", self.grid[row][column]);
+        for row in 0..self.num_rows {
+            for column in 0..self.num_cols {
+                print!("{} ", self.cell(row, column));
             }
             println!();
         }
     }
 
-    pub fn draw(&self) {
-        for row in 0..self.num_rows {
-            for column in 0..self.num_cols {
-                let cell_value = self.grid[row as usize][column as usize];
-                unsafe {
-                    DrawRectangle(
-                        column * self.cell_size + 11,
-                        row * self.cell_size + 11,
-                        self.cell_size - 1,
-                        self.cell_size - 1,
-                        self.colors[cell_value as usize],
-                    );
-                }
-            }
+    /// The value of a cell inside the grid.
+    pub fn cell(&self, row: c_int, column: c_int) -> c_int {
+        self.cells[self.index(row, column)]
+    }
+
+    /// Sets a cell inside the grid, 0 emptying it.
+    pub fn set_cell(&mut self, row: c_int, column: c_int, value: c_int) {
+        let index = self.index(row, column);
+        self.cells[index] = value;
+        if value == 0 {
+            self.rows[row as usize] &= !(1 << column);
+        } else {
+            self.rows[row as usize] |= 1 << column;
         }
     }
 
@@ -72,49 +94,256 @@ impl Grid {
     }
 
     pub fn is_cell_empty(&self, row: c_int, column: c_int) -> bool {
-        if self.grid[row as usize][column as usize] == 0 {
-            return true;
+        self.rows[row as usize] & (1 << column) == 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&row| row == 0)
+    }
+
+    /// Whether `mask` lies inside the grid without overlapping a filled cell
+    /// when its box is at `(row, column)`.
+    pub fn fits(&self, mask: &PieceMask, row: c_int, column: c_int) -> bool {
+        if column + mask.left < 0 || column + mask.right >= self.num_cols {
+            return false;
+        }
+
+        let top = row + mask.top;
+        if top < 0 || top + mask.rows.len() as c_int > self.num_rows {
+            return false;
+        }
+
+        mask.rows
+            .iter()
+            .zip(&self.rows[top as usize..])
+            .all(|(&piece_row, &grid_row)| shift(piece_row, column) & grid_row == 0)
+    }
+
+    /// Number of rows `mask` can fall from `(row, column)` before it lands.
+    pub fn drop_distance(&self, mask: &PieceMask, row: c_int, column: c_int) -> c_int {
+        if !self.fits(mask, row, column) {
+            return 0;
+        }
+
+        // The piece falls freely until its bottom row reaches the first
+        // filled row, so only the rows from there on need checking.
+        let top = (row + mask.top) as usize;
+        let height = mask.rows.len();
+        let last_top = self.rows.len() - height;
+        let first_filled = self.rows[top..]
+            .iter()
+            .position(|&grid_row| grid_row != 0)
+            .map_or(self.rows.len(), |filled| top + filled);
+        let mut distance = first_filled.saturating_sub(top + height);
+        while top + distance < last_top {
+            let below = &self.rows[top + distance + 1..];
+            let lands = mask
+                .rows
+                .iter()
+                .zip(below)
+                .any(|(&piece_row, &grid_row)| shift(piece_row, column) & grid_row != 0);
+            if lands {
+                break;
+            }
+            distance += 1;
         }
 
-        false
+        distance as c_int
     }
 
+    /// Fills the cells of `mask` with its box at `(row, column)`, which
+    /// must lie inside the grid.
+    pub fn place(&mut self, mask: &PieceMask, row: c_int, column: c_int, value: c_int) {
+        for (index, &piece_row) in mask.rows.iter().enumerate() {
+            let grid_row = row + mask.top + index as c_int;
+            let bits = shift(piece_row, column);
+            self.rows[grid_row as usize] |= bits;
+
+            let mut remaining = bits;
+            while remaining != 0 {
+                let index = self.index(grid_row, remaining.trailing_zeros() as c_int);
+                self.cells[index] = value;
+                remaining &= remaining - 1;
+            }
+        }
+    }
+
+    /// Removes every full row, moving the rows above down, and returns how
+    /// many were removed.
     pub fn clear_full_rows(&mut self) -> c_int {
-        let mut completed = 0;
-
-        for row in (0..=self.num_rows - 1).rev() {
-            if self.is_row_full(row) {
-                self.clear_row(row);
-                completed += 1;
-            } else if completed > 0 {
-                self.move_row_down(row, completed);
+        let full = self.full_row();
+        if !self.rows.contains(&full) {
+            return 0;
+        }
+
+        let width = self.num_cols as usize;
+        let mut target = self.num_rows as usize;
+
+        for row in (0..self.num_rows as usize).rev() {
+            if self.rows[row] == full {
+                continue;
+            }
+
+            target -= 1;
+            if target != row {
+                self.rows[target] = self.rows[row];
+                self.cells
+                    .copy_within(row * width..(row + 1) * width, target * width);
             }
         }
 
-        completed
+        self.rows[..target].fill(0);
+        self.cells[..target * width].fill(0);
+
+        target as c_int
     }
 
-    fn is_row_full(&self, row: c_int) -> bool {
-        for column in 0..self.num_cols {
-            if self.grid[row as usize][column as usize] == 0 {
-                return false;
+    pub fn is_row_empty(&self, row: c_int) -> bool {
+        self.rows[row as usize] == 0
+    }
+
+    /// Pushes everything up by `rows` and fills the bottom rows with `value`,
+    /// leaving `hole_column` empty. Cells pushed out of the top are lost.
+    pub fn add_garbage(&mut self, rows: c_int, hole_column: c_int, value: c_int) {
+        let rows = rows.clamp(0, self.num_rows) as usize;
+        let width = self.num_cols as usize;
+
+        self.rows.copy_within(rows.., 0);
+        self.cells.copy_within(rows * width.., 0);
+
+        let garbage = self.full_row() & !shift(1, hole_column);
+        let first = self.num_rows as usize - rows;
+        for row in first..self.num_rows as usize {
+            self.rows[row] = garbage;
+            for column in 0..width {
+                self.cells[row * width + column] = if garbage & (1 << column) != 0 {
+                    value
+                } else {
+                    0
+                };
             }
         }
+    }
 
-        true
+    fn full_row(&self) -> u64 {
+        shift(u64::MAX, self.num_cols - MAX_COLUMNS)
     }
 
-    fn clear_row(&mut self, row: c_int) {
-        for column in 0..self.num_cols {
-            self.grid[row as usize][column as usize] = 0;
+    fn index(&self, row: c_int, column: c_int) -> usize {
+        (row * self.num_cols + column) as usize
+    }
+}
+
+/// Moves the bits of a box row to where they sit with the box at `column`.
+fn shift(row: u64, column: c_int) -> u64 {
+    if column >= 0 {
+        row.checked_shl(column as u32).unwrap_or(0)
+    } else {
+        row.checked_shr(-column as u32).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+
+    fn fill_row(grid: &mut Grid, row: c_int, value: c_int) {
+        for column in 0..grid.num_cols {
+            grid.set_cell(row, column, value);
         }
     }
 
-    fn move_row_down(&mut self, row: c_int, num_rows: c_int) {
-        for column in 0..self.num_cols {
-            self.grid[(row + num_rows) as usize][column as usize] =
-                self.grid[row as usize][column as usize];
-            self.grid[row as usize][column as usize] = 0;
+    fn mask(cells: &[(c_int, c_int)]) -> PieceMask {
+        let cells: Vec<_> = cells
+            .iter()
+            .map(|&(row, column)| Position::new(row, column))
+            .collect();
+        PieceMask::new(&cells)
+    }
+
+    #[test]
+    fn clear_full_rows_moves_rows_down() {
+        let mut grid = Grid::new(4, 4, 0);
+        fill_row(&mut grid, 3, 1);
+        grid.set_cell(2, 1, 2);
+        fill_row(&mut grid, 1, 3);
+        grid.set_cell(0, 0, 4);
+
+        assert_eq!(grid.clear_full_rows(), 2);
+        assert_eq!(grid.cell(3, 1), 2);
+        assert_eq!(grid.cell(2, 0), 4);
+        assert!(grid.is_cell_empty(3, 0));
+        assert!(grid.is_row_empty(0));
+        assert!(grid.is_row_empty(1));
+    }
+
+    #[test]
+    fn clear_full_rows_without_full_rows() {
+        let mut grid = Grid::new(4, 4, 0);
+        grid.set_cell(3, 0, 1);
+        assert_eq!(grid.clear_full_rows(), 0);
+        assert_eq!(grid.cell(3, 0), 1);
+    }
+
+    #[test]
+    fn clearing_everything_empties_the_grid() {
+        let mut grid = Grid::new(2, 64, 0);
+        fill_row(&mut grid, 1, 1);
+        assert_eq!(grid.clear_full_rows(), 1);
+        assert!(grid.is_empty());
+    }
+
+    #[test]
+    fn add_garbage_pushes_rows_up() {
+        let mut grid = Grid::new(4, 4, 0);
+        grid.set_cell(0, 2, 5);
+        grid.set_cell(3, 1, 1);
+
+        grid.add_garbage(2, 3, 9);
+        assert_eq!(grid.cell(1, 1), 1);
+        for row in 2..4 {
+            assert_eq!(grid.cell(row, 0), 9);
+            assert_eq!(grid.cell(row, 2), 9);
+            assert!(grid.is_cell_empty(row, 3));
         }
+        // The cell in the top row was pushed out.
+        assert!((0..4).all(|column| grid.cell(0, column) != 5));
+    }
+
+    #[test]
+    fn add_garbage_is_clamped_to_the_grid() {
+        let mut grid = Grid::new(3, 4, 0);
+        grid.add_garbage(10, 0, 9);
+        assert!((0..3).all(|row| grid.is_cell_empty(row, 0) && grid.cell(row, 1) == 9));
+    }
+
+    #[test]
+    fn fits_checks_walls_and_cells() {
+        let mut grid = Grid::new(4, 4, 0);
+        let square = mask(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert!(grid.fits(&square, 0, 0));
+        assert!(!grid.fits(&square, 0, 3));
+        assert!(!grid.fits(&square, 3, 0));
+
+        grid.set_cell(1, 1, 1);
+        assert!(!grid.fits(&square, 0, 0));
+        assert!(grid.fits(&square, 0, 2));
+    }
+
+    #[test]
+    fn drop_distance_lands_on_cells() {
+        let mut grid = Grid::new(8, 4, 2);
+        let bar = mask(&[(1, 0), (1, 1), (1, 2)]);
+        assert_eq!(grid.drop_distance(&bar, 0, 0), 8);
+
+        grid.set_cell(6, 3, 1);
+        assert_eq!(grid.drop_distance(&bar, 0, 1), 4);
+        // Only a cell under the piece stops it.
+        assert_eq!(grid.drop_distance(&bar, 0, 0), 8);
+
+        grid.place(&bar, 4, 0, 2);
+        assert_eq!(grid.cell(5, 0), 2);
+        assert_eq!(grid.drop_distance(&bar, 0, 0), 3);
     }
 }
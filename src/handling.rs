@@ -0,0 +1,35 @@
+/// The longest any handling setting may be, in logic frames: one second.
+pub const MAX_FRAMES: u32 = 60;
+
+/// How held keys repeat, tuned per player. All values are in logic frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handling {
+    /// Delayed auto shift: frames a direction must be held before it
+    /// starts repeating.
+    pub das: u32,
+    /// Auto repeat rate: frames between repeated shifts once DAS has
+    /// charged. Zero moves the piece straight to the wall.
+    pub arr: u32,
+    /// Frames between rows while soft drop is held.
+    pub soft_drop_interval: u32,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Self {
+            das: 10,
+            arr: 2,
+            soft_drop_interval: 2,
+        }
+    }
+}
+
+impl Handling {
+    /// Whether every value is one a player may choose: at most
+    /// [`MAX_FRAMES`], with soft drop moving at least every frame.
+    pub fn is_valid(&self) -> bool {
+        self.das <= MAX_FRAMES
+            && self.arr <= MAX_FRAMES
+            && (1..=MAX_FRAMES).contains(&self.soft_drop_interval)
+    }
+}
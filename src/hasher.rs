@@ -0,0 +1,34 @@
+/// 64-bit FNV-1a, which unlike the standard library's hasher is fixed
+/// across Rust releases.
+pub(crate) struct StateHasher {
+    hash: u64,
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self {
+            hash: 0xCBF2_9CE4_8422_2325,
+        }
+    }
+}
+
+impl StateHasher {
+    pub fn write(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+
+    /// Writes whether `value` is present, then the value if it is.
+    pub fn write_option(&mut self, value: Option<u64>) {
+        self.write(value.is_some() as u64);
+        if let Some(value) = value {
+            self.write(value);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
@@ -0,0 +1,33 @@
+/// An action the player can ask the game to perform, independent of the
+/// device it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    Rotate,
+    RotateCounterClockwise,
+    Hold,
+}
+
+impl Input {
+    pub const ALL: [Input; 7] = [
+        Input::MoveLeft,
+        Input::MoveRight,
+        Input::SoftDrop,
+        Input::HardDrop,
+        Input::Rotate,
+        Input::RotateCounterClockwise,
+        Input::Hold,
+    ];
+}
+
+/// A press or release of an input, stamped with the logic frame it came in
+/// before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub frame: u64,
+    pub input: Input,
+    pub pressed: bool,
+}
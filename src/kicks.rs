@@ -0,0 +1,116 @@
+use std::os::raw::c_int;
+
+/// Which Super Rotation System wall kick table a piece rotates with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KickTable {
+    #[default]
+    Jlstz,
+    I,
+    None,
+}
+
+/// Offsets are `(x, y)` with `y` pointing up, exactly as the guideline lists
+/// them. Each row is tried in order until the rotated piece fits.
+type Kicks = [(c_int, c_int); 5];
+
+const JLSTZ_CLOCKWISE: [Kicks; 4] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const JLSTZ_COUNTER_CLOCKWISE: [Kicks; 4] = [
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const I_CLOCKWISE: [Kicks; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+];
+
+const I_COUNTER_CLOCKWISE: [Kicks; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+];
+
+const NO_KICKS: [(c_int, c_int); 1] = [(0, 0)];
+
+impl KickTable {
+    /// Returns the offsets to try, in order, when rotating from rotation
+    /// state `from` (0 = spawn, 1 = R, 2 = 2, 3 = L).
+    pub fn offsets(self, from: c_int, clockwise: bool) -> &'static [(c_int, c_int)] {
+        let from = from.rem_euclid(4) as usize;
+
+        match (self, clockwise) {
+            (KickTable::Jlstz, true) => &JLSTZ_CLOCKWISE[from],
+            (KickTable::Jlstz, false) => &JLSTZ_COUNTER_CLOCKWISE[from],
+            (KickTable::I, true) => &I_CLOCKWISE[from],
+            (KickTable::I, false) => &I_COUNTER_CLOCKWISE[from],
+            (KickTable::None, _) => &NO_KICKS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jlstz_spawn_to_right() {
+        assert_eq!(
+            KickTable::Jlstz.offsets(0, true),
+            &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]
+        );
+    }
+
+    #[test]
+    fn jlstz_spawn_to_left() {
+        assert_eq!(
+            KickTable::Jlstz.offsets(0, false),
+            &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]
+        );
+    }
+
+    #[test]
+    fn i_spawn_to_right() {
+        assert_eq!(
+            KickTable::I.offsets(0, true),
+            &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn rotating_back_reverses_the_kicks() {
+        // Turning 0 -> R and then R -> 0 tries opposite offsets, in order.
+        for table in [KickTable::Jlstz, KickTable::I] {
+            for from in 0..4 {
+                let there = table.offsets(from, true);
+                let back = table.offsets(from + 1, false);
+                for (&(x, y), &(back_x, back_y)) in there.iter().zip(back) {
+                    assert_eq!((x, y), (-back_x, -back_y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn states_wrap_around() {
+        assert_eq!(
+            KickTable::Jlstz.offsets(4, true),
+            KickTable::Jlstz.offsets(0, true)
+        );
+        assert_eq!(
+            KickTable::Jlstz.offsets(-1, true),
+            KickTable::Jlstz.offsets(3, true)
+        );
+        assert_eq!(KickTable::None.offsets(2, false), &[(0, 0)]);
+    }
+}
@@ -0,0 +1,20 @@
+pub mod block;
+pub mod clock;
+pub mod game;
+pub mod grid;
+pub mod handling;
+mod hasher;
+pub mod input;
+pub mod kicks;
+pub mod mode;
+pub mod pieces;
+pub mod position;
+pub mod randomizer;
+pub mod replay;
+pub mod rng;
+pub mod rules;
+pub mod save;
+pub mod scoring;
+pub mod settings;
+pub mod statistics;
+pub mod text;
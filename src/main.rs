@@ -1,137 +1,87 @@
-use std::ffi::CString;
+use std::{env, ffi::CString, process};
 
-use colors::{DARK_BLUE, LIGHT_BLUE};
-use game::Game;
+use app::App;
 use raylib::{
     ffi::{
-        BeginDrawing, ClearBackground, CloseWindow, DrawRectangleRounded, DrawTextEx, EndDrawing,
-        GetTime, InitWindow, LoadFontEx, MeasureTextEx, Rectangle, SetTargetFPS, UpdateMusicStream,
-        Vector2, WindowShouldClose,
+        BeginDrawing, CloseWindow, EndDrawing, InitWindow, SetExitKey, SetTargetFPS,
+        WindowShouldClose,
     },
-    prelude::Color,
+    prelude::KeyboardKey,
 };
+use renderer::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use tetris::{mode::Mode, pieces, replay};
 
-static mut LAST_UPDATE_TIME: f64 = 0.0;
-
-mod block;
+mod app;
+mod audio;
 mod colors;
-mod game;
-mod grid;
-mod position;
-
-fn event_triggered(interval: f64) -> bool {
-    let current_time = unsafe { GetTime() };
-    if unsafe { current_time - LAST_UPDATE_TIME >= interval } {
-        unsafe {
-            LAST_UPDATE_TIME = current_time;
-        }
-
-        return true;
-    }
-
-    false
-}
+mod renderer;
 
 fn main() {
+    // Either a seed for new games, or `--replay FILE` to watch a replay.
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let (seed, replay_path) = match arguments.as_slice() {
+        [] => (None, None),
+        [flag, path] if flag == "--replay" => (None, Some(path.as_str())),
+        [seed] => match seed.parse() {
+            Ok(seed) => (Some(seed), None),
+            Err(_) => usage(),
+        },
+        _ => usage(),
+    };
+    let replay = replay_path.map(|path| {
+        replay::read(path).unwrap_or_else(|error| {
+            eprintln!("{path}: {error}");
+            process::exit(1);
+        })
+    });
+
+    let piece_sets = Mode::ALL
+        .iter()
+        .map(|mode| {
+            let path = mode.pieces_path();
+            pieces::load(path).unwrap_or_else(|error| {
+                eprintln!("{path}: {error}");
+                process::exit(1);
+            })
+        })
+        .collect();
+
     unsafe {
         let window_title = CString::new("Tetris").unwrap();
-        InitWindow(500, 620, window_title.as_ptr());
+        InitWindow(WINDOW_WIDTH, WINDOW_HEIGHT, window_title.as_ptr());
         SetTargetFPS(60);
+        SetExitKey(KeyboardKey::KEY_NULL as i32);
+    }
 
-        let font_file_name = CString::new("assets/font/monogram.ttf").unwrap();
-
-        let font = LoadFontEx(font_file_name.as_ptr(), 64, std::ptr::null_mut(), 0);
+    {
+        let mut app = App::new(seed, piece_sets);
+        if let Some(replay) = replay {
+            app.watch_replay(replay);
+        }
 
-        let mut game = Game::new();
+        while unsafe { !WindowShouldClose() } {
+            app.update();
 
-        while !WindowShouldClose() {
-            UpdateMusicStream(game.music);
-            game.handle_input();
-            if event_triggered(0.2) {
-                game.move_block_down();
+            unsafe {
+                BeginDrawing();
             }
-
-            BeginDrawing();
-            ClearBackground(DARK_BLUE);
-
-            let score_text = CString::new("Score").unwrap();
-            DrawTextEx(
-                font,
-                score_text.as_ptr(),
-                Vector2 { x: 365.0, y: 15.0 },
-                38.0,
-                2.0,
-                Color::WHITE.into(),
-            );
-
-            let next_text = CString::new("Next").unwrap();
-            DrawTextEx(
-                font,
-                next_text.as_ptr(),
-                Vector2 { x: 370.0, y: 175.0 },
-                38.0,
-                2.0,
-                Color::WHITE.into(),
-            );
-
-            if game.game_over {
-                let game_over_text = CString::new("GAME OVER").unwrap();
-                DrawTextEx(
-                    font,
-                    game_over_text.as_ptr(),
-                    Vector2 { x: 320.0, y: 450.0 },
-                    38.0,
-                    2.0,
-                    Color::WHITE.into(),
-                );
+            app.draw();
+            unsafe {
+                EndDrawing();
             }
-
-            DrawRectangleRounded(
-                Rectangle {
-                    x: 320.0,
-                    y: 55.0,
-                    width: 170.0,
-                    height: 60.0,
-                },
-                0.3,
-                6,
-                LIGHT_BLUE,
-            );
-
-            let score_text = {
-                let text = format!("{}", game.score);
-
-                CString::new(text).unwrap()
-            };
-
-            let text_size = MeasureTextEx(font, score_text.as_ptr(), 38.0, 2.0);
-
-            DrawTextEx(
-                font,
-                score_text.as_ptr(),
-                Vector2 {
-                    x: 320.0 + (170.0 - text_size.x) / 2.0,
-                    y: 65.0,
-                },
-                38.0,
-                2.0,
-                Color::WHITE.into(),
-            );
-            DrawRectangleRounded(
-                Rectangle {
-                    x: 320.0,
-                    y: 215.0,
-                    width: 170.0,
-                    height: 180.0,
-                },
-                0.3,
-                6,
-                LIGHT_BLUE,
-            );
-            game.draw();
-            EndDrawing();
         }
 
+        // Keep a game in progress for the next launch.
+        app.save_game();
+    }
+
+    unsafe {
         CloseWindow();
     }
 }
+
+/// Prints how to start the game and exits.
+fn usage() -> ! {
+    eprintln!("usage: tetris [SEED | --replay FILE]");
+    process::exit(1);
+}
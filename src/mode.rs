@@ -0,0 +1,83 @@
+use crate::{pieces, randomizer::RandomizerKind, rules::Rules, scoring::ScoreTable};
+
+/// A way to play, each with its own rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Marathon,
+    Master,
+    Classic,
+    Wide,
+    Tall,
+    Mini,
+    Pentris,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 7] = [
+        Mode::Marathon,
+        Mode::Master,
+        Mode::Classic,
+        Mode::Wide,
+        Mode::Tall,
+        Mode::Mini,
+        Mode::Pentris,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Marathon => "Marathon",
+            Mode::Master => "Master",
+            Mode::Classic => "Classic",
+            Mode::Wide => "Wide",
+            Mode::Tall => "Tall",
+            Mode::Mini => "Mini",
+            Mode::Pentris => "Pentris",
+        }
+    }
+
+    /// Piece file the game loads this mode's piece set from.
+    pub fn pieces_path(&self) -> &'static str {
+        match self {
+            Mode::Pentris => "assets/pieces/pentominoes.txt",
+            _ => "assets/pieces/tetrominoes.txt",
+        }
+    }
+
+    pub fn rules(&self) -> Rules {
+        match self {
+            Mode::Marathon => Rules::default(),
+            Mode::Master => Rules {
+                start_level: 15,
+                randomizer: RandomizerKind::History {
+                    length: 4,
+                    rerolls: 6,
+                },
+                ..Rules::default()
+            },
+            Mode::Classic => Rules {
+                preview_count: 1,
+                randomizer: RandomizerKind::Random,
+                ..Rules::default()
+            },
+            Mode::Wide => Rules {
+                board_width: 16,
+                ..Rules::default()
+            },
+            Mode::Tall => Rules {
+                board_height: 30,
+                ..Rules::default()
+            },
+            Mode::Mini => Rules {
+                board_width: 6,
+                board_height: 12,
+                ..Rules::default()
+            },
+            Mode::Pentris => Rules {
+                board_width: 12,
+                pieces: pieces::pentominoes(),
+                scoring: ScoreTable::pentris(),
+                ..Rules::default()
+            },
+        }
+    }
+}
@@ -0,0 +1,528 @@
+use std::{fs, os::raw::c_int, path::Path};
+
+use crate::{
+    block::Block,
+    grid::MAX_COLUMNS,
+    hasher::StateHasher,
+    kicks::KickTable,
+    position::Position,
+    text::{Invalid, TextError},
+};
+
+/// The piece sets the modes use, built into the game so it can always start.
+const STANDARD_PIECES: &str = include_str!("../assets/pieces/tetrominoes.txt");
+const PENTOMINOES: &str = include_str!("../assets/pieces/pentominoes.txt");
+
+/// The seven guideline tetrominoes.
+pub fn standard() -> Vec<Block> {
+    parse(STANDARD_PIECES).expect("built-in piece set is valid")
+}
+
+/// The eighteen one-sided pentominoes.
+pub fn pentominoes() -> Vec<Block> {
+    parse(PENTOMINOES).expect("built-in piece set is valid")
+}
+
+/// A fingerprint of a piece set covering everything that affects play, so
+/// a replay can tell whether it is played back with the pieces it was
+/// recorded with. Names and colours are left out.
+pub fn fingerprint(pieces: &[Block]) -> u64 {
+    let mut hasher = StateHasher::default();
+    for piece in pieces {
+        piece.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+/// Reads and parses the piece file at `path`.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Block>, TextError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Parses a piece file, see `assets/pieces/tetrominoes.txt` for the format.
+/// Pieces get ids from 1 in the order they are listed.
+pub fn parse(text: &str) -> Result<Vec<Block>, TextError> {
+    let mut pieces: Vec<PieceDefinition> = Vec::new();
+    let mut in_state = false;
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            in_state = false;
+            continue;
+        }
+
+        if in_state && (line.starts_with('#') || line.starts_with('.')) {
+            let piece = pieces.last_mut().unwrap();
+            piece.states.last_mut().unwrap().rows.push((number, line));
+            continue;
+        }
+        in_state = false;
+
+        let mut words = line.split_whitespace();
+        let directive = words.next().unwrap();
+        let arguments: Vec<&str> = words.collect();
+
+        if directive == "piece" {
+            let [name] = arguments[..] else {
+                return Err(Invalid::new(number, "expected `piece NAME`").into());
+            };
+            if pieces.iter().any(|piece| piece.name == name) {
+                return Err(
+                    Invalid::new(number, format!("piece `{name}` is defined twice")).into(),
+                );
+            }
+
+            pieces.push(PieceDefinition::new(name, number));
+            continue;
+        }
+
+        let Some(piece) = pieces.last_mut() else {
+            return Err(
+                Invalid::new(number, format!("`{directive}` before the first `piece`")).into(),
+            );
+        };
+
+        match directive {
+            "color" => {
+                let components = parse_numbers::<u8>(&arguments, 3).ok_or_else(|| {
+                    Invalid::new(number, "expected `color R G B` with values 0 to 255")
+                })?;
+                piece.color = Some([components[0], components[1], components[2]]);
+            }
+            "kicks" => {
+                let kick_table = match arguments[..] {
+                    ["jlstz"] => KickTable::Jlstz,
+                    ["i"] => KickTable::I,
+                    ["none"] => KickTable::None,
+                    _ => {
+                        return Err(Invalid::new(
+                            number,
+                            "expected `kicks jlstz`, `kicks i` or `kicks none`",
+                        )
+                        .into())
+                    }
+                };
+                piece.kick_table = Some(kick_table);
+            }
+            "spawn" => {
+                let offset = parse_numbers::<c_int>(&arguments, 2)
+                    .ok_or_else(|| Invalid::new(number, "expected `spawn ROW COLUMN`"))?;
+                piece.spawn = Some((offset[0], offset[1]));
+            }
+            "t-spin" if arguments.is_empty() => piece.t_spin = true,
+            "state" | "shape" if arguments.is_empty() => {
+                if piece.shape || (directive == "shape" && !piece.states.is_empty()) {
+                    return Err(Invalid::new(
+                        number,
+                        "a piece takes either one `shape` or its `state`s",
+                    )
+                    .into());
+                }
+
+                piece.shape = directive == "shape";
+                piece.states.push(StateDefinition {
+                    line: number,
+                    rows: Vec::new(),
+                });
+                in_state = true;
+            }
+            _ => return Err(Invalid::new(number, format!("unexpected `{line}`")).into()),
+        }
+    }
+
+    if pieces.is_empty() {
+        return Err(Invalid::new(1, "no pieces defined").into());
+    }
+
+    pieces
+        .iter()
+        .enumerate()
+        .map(|(index, piece)| Ok(piece.build(index as c_int + 1)?))
+        .collect()
+}
+
+/// A piece as read from the file, before it is checked.
+struct PieceDefinition<'a> {
+    name: &'a str,
+    line: usize,
+    color: Option<[u8; 3]>,
+    kick_table: Option<KickTable>,
+    spawn: Option<(c_int, c_int)>,
+    t_spin: bool,
+    /// The single state is a base shape to generate the rotations from.
+    shape: bool,
+    states: Vec<StateDefinition<'a>>,
+}
+
+struct StateDefinition<'a> {
+    line: usize,
+    rows: Vec<(usize, &'a str)>,
+}
+
+impl<'a> PieceDefinition<'a> {
+    fn new(name: &'a str, line: usize) -> Self {
+        Self {
+            name,
+            line,
+            color: None,
+            kick_table: None,
+            spawn: None,
+            t_spin: false,
+            shape: false,
+            states: Vec::new(),
+        }
+    }
+
+    fn build(&self, id: c_int) -> Result<Block, Invalid> {
+        let missing =
+            |what: &str| Invalid::new(self.line, format!("piece `{}` has no {what}", self.name));
+        let color = self.color.ok_or_else(|| missing("color"))?;
+        let kick_table = self.kick_table.ok_or_else(|| missing("kicks"))?;
+        let (spawn_row, spawn_column) = self.spawn.ok_or_else(|| missing("spawn"))?;
+        if self.states.is_empty() {
+            return Err(missing("states"));
+        }
+
+        let states = if self.shape {
+            self.generate_states()?
+        } else {
+            self.read_states()?
+        };
+        // Spins are detected from the corners of a 3 by 3 box, which only
+        // make sense for a piece that turns through all four states.
+        let first = &self.states[0];
+        let box_size = (
+            first.rows.len(),
+            first.rows.first().map_or(0, |row| row.1.len()),
+        );
+        if self.t_spin && (box_size != (3, 3) || states.len() != 4) {
+            return Err(Invalid::new(
+                self.line,
+                format!(
+                    "piece `{}` needs a 3 by 3 box and 4 states to use `t-spin`",
+                    self.name
+                ),
+            ));
+        }
+
+        let mut block = Block::new();
+        block.id = id;
+        block.name = self.name.to_string();
+        block.color = color;
+        block.set_cells(states);
+        block.kick_table = kick_table;
+        block.t_spin = self.t_spin;
+        block.set_spawn(spawn_row, spawn_column);
+
+        Ok(block)
+    }
+
+    /// Rotates the base shape around the centre of its box.
+    fn generate_states(&self) -> Result<Vec<Vec<Position>>, Invalid> {
+        let shape = &self.states[0];
+        let size = shape.rows.len();
+        if shape.rows.first().is_some_and(|row| row.1.len() != size) {
+            return Err(Invalid::new(
+                shape.line,
+                "shape box must be square to rotate",
+            ));
+        }
+
+        Ok(rotation_states(&shape.cells()?, size as c_int))
+    }
+
+    /// Reads states listed one by one, which must agree in box size and
+    /// cell count.
+    fn read_states(&self) -> Result<Vec<Vec<Position>>, Invalid> {
+        if ![1, 2, 4].contains(&self.states.len()) {
+            return Err(Invalid::new(
+                self.line,
+                format!(
+                    "piece `{}` has {} states, expected 1, 2 or 4",
+                    self.name,
+                    self.states.len()
+                ),
+            ));
+        }
+
+        let mut states = Vec::new();
+        let mut size = None;
+        let mut cell_count = None;
+        for state in &self.states {
+            let state_size = (
+                state.rows.len(),
+                state.rows.first().map_or(0, |row| row.1.len()),
+            );
+            if *size.get_or_insert(state_size) != state_size {
+                return Err(Invalid::new(
+                    state.line,
+                    "state box differs in size from the first state",
+                ));
+            }
+
+            let positions = state.cells()?;
+            let first_count = *cell_count.get_or_insert(positions.len());
+            if positions.len() != first_count {
+                return Err(Invalid::new(
+                    state.line,
+                    format!(
+                        "state has {} cells but the first state has {first_count}",
+                        positions.len()
+                    ),
+                ));
+            }
+
+            states.push(positions);
+        }
+
+        Ok(states)
+    }
+}
+
+impl StateDefinition<'_> {
+    /// Reads the cells of the state, which must form one connected shape.
+    fn cells(&self) -> Result<Vec<Position>, Invalid> {
+        let Some(&(_, first_row)) = self.rows.first() else {
+            return Err(Invalid::new(self.line, "state has no rows"));
+        };
+
+        if first_row.len() > MAX_COLUMNS as usize {
+            return Err(Invalid::new(
+                self.line,
+                format!("state is wider than {MAX_COLUMNS} columns"),
+            ));
+        }
+
+        let mut positions = Vec::new();
+        for (row, &(line, text)) in self.rows.iter().enumerate() {
+            if text.len() != first_row.len() {
+                return Err(Invalid::new(
+                    line,
+                    "row differs in length from the first row",
+                ));
+            }
+
+            for (column, character) in text.chars().enumerate() {
+                match character {
+                    '#' => positions.push(Position::new(row as c_int, column as c_int)),
+                    '.' => (),
+                    _ => {
+                        return Err(Invalid::new(
+                            line,
+                            format!("unexpected `{character}`, cells are `#` or `.`"),
+                        ))
+                    }
+                }
+            }
+        }
+
+        if positions.is_empty() {
+            return Err(Invalid::new(self.line, "state has no cells"));
+        }
+        if !is_connected(&positions) {
+            return Err(Invalid::new(self.line, "state cells are not all connected"));
+        }
+
+        Ok(positions)
+    }
+}
+
+/// Derives the clockwise rotation states of `cells` turned inside a
+/// `size` by `size` box, starting with `cells` itself. States repeated by a
+/// symmetric shape are left out, so this returns one, two or four states.
+pub fn rotation_states(cells: &[Position], size: c_int) -> Vec<Vec<Position>> {
+    let mut states = vec![sorted(cells.to_vec())];
+    for _ in 1..4 {
+        let rotated = states
+            .last()
+            .unwrap()
+            .iter()
+            .map(|position| Position::new(position.column, size - 1 - position.row))
+            .collect();
+        states.push(sorted(rotated));
+    }
+
+    if states[1] == states[0] {
+        states.truncate(1);
+    } else if states[2] == states[0] {
+        states.truncate(2);
+    }
+
+    states
+}
+
+fn sorted(mut positions: Vec<Position>) -> Vec<Position> {
+    positions.sort_by_key(|position| (position.row, position.column));
+    positions
+}
+
+/// Whether every cell can be reached from the first through edge-adjacent
+/// cells.
+fn is_connected(positions: &[Position]) -> bool {
+    let mut reached = vec![false; positions.len()];
+    let mut pending = vec![0];
+    reached[0] = true;
+
+    while let Some(index) = pending.pop() {
+        let current = positions[index];
+        for (other, position) in positions.iter().enumerate() {
+            let distance =
+                (position.row - current.row).abs() + (position.column - current.column).abs();
+            if distance == 1 && !reached[other] {
+                reached[other] = true;
+                pending.push(other);
+            }
+        }
+    }
+
+    reached.iter().all(|&reached| reached)
+}
+
+/// Parses exactly `count` whitespace separated numbers.
+fn parse_numbers<T: std::str::FromStr>(arguments: &[&str], count: usize) -> Option<Vec<T>> {
+    if arguments.len() != count {
+        return None;
+    }
+
+    arguments
+        .iter()
+        .map(|argument| argument.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "piece X\ncolor 1 2 3\nkicks jlstz\nspawn 0 3\n";
+
+    /// The line and message `text` is rejected with.
+    fn error(text: &str) -> (usize, String) {
+        match parse(text) {
+            Err(TextError::Invalid { line, message }) => (line, message),
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    fn positions(cells: &[(c_int, c_int)]) -> Vec<Position> {
+        cells
+            .iter()
+            .map(|&(row, column)| Position::new(row, column))
+            .collect()
+    }
+
+    #[test]
+    fn built_in_sets_parse() {
+        let standard = standard();
+        assert_eq!(standard.len(), 7);
+        assert_eq!(standard.iter().filter(|piece| piece.t_spin).count(), 1);
+        assert_eq!(pentominoes().len(), 18);
+    }
+
+    #[test]
+    fn shape_generates_rotations() {
+        let pieces = parse(&format!("{HEADER}t-spin\nshape\n.#.\n###\n...\n")).unwrap();
+        assert_eq!(pieces[0].id, 1);
+        assert_eq!(pieces[0].rotation_count(), 4);
+        assert!(pieces[0].t_spin);
+    }
+
+    #[test]
+    fn rotation_states_drop_repeats() {
+        let square = positions(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert_eq!(rotation_states(&square, 2).len(), 1);
+
+        let line = positions(&[(1, 0), (1, 1), (1, 2)]);
+        let states = rotation_states(&line, 3);
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[1], positions(&[(0, 1), (1, 1), (2, 1)]));
+
+        let corner = positions(&[(0, 0), (1, 0), (1, 1)]);
+        assert_eq!(rotation_states(&corner, 2).len(), 4);
+    }
+
+    #[test]
+    fn rejects_bad_directives() {
+        assert_eq!(error(""), (1, "no pieces defined".to_string()));
+        assert_eq!(
+            error("color 1 2 3\n"),
+            (1, "`color` before the first `piece`".to_string())
+        );
+        assert_eq!(error("piece X\ncolor 1 2 300\n").0, 2);
+        assert_eq!(error("piece X\nkicks sideways\n").0, 2);
+        assert_eq!(error("piece X\nspawn 1\n").0, 2);
+        assert_eq!(
+            error("piece X\nwobble\n"),
+            (2, "unexpected `wobble`".to_string())
+        );
+        assert_eq!(
+            error(&format!("{HEADER}shape\n#\npiece X\n")),
+            (7, "piece `X` is defined twice".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_pieces() {
+        assert_eq!(
+            error("piece X\ncolor 1 2 3\nkicks none\nshape\n#\n"),
+            (1, "piece `X` has no spawn".to_string())
+        );
+        assert_eq!(error(HEADER), (1, "piece `X` has no states".to_string()));
+    }
+
+    #[test]
+    fn rejects_bad_states() {
+        assert_eq!(
+            error(&format!("{HEADER}state\n#.\n#\n")).1,
+            "row differs in length from the first row"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}state\n#x\n")).1,
+            "unexpected `x`, cells are `#` or `.`"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}state\n..\n")).1,
+            "state has no cells"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}state\n#.\n.#\n")).1,
+            "state cells are not all connected"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}shape\n##.\n")).1,
+            "shape box must be square to rotate"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}state\n#\nstate\n#\nstate\n#\n")).1,
+            "piece `X` has 3 states, expected 1, 2 or 4"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}state\n##\nstate\n#.\n#.\n")).1,
+            "state box differs in size from the first state"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}state\n##\nstate\n#.\n")).1,
+            "state has 1 cells but the first state has 2"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}shape\n#\nstate\n#\n")).1,
+            "a piece takes either one `shape` or its `state`s"
+        );
+    }
+
+    #[test]
+    fn t_spin_needs_a_three_by_three_piece_with_four_states() {
+        let message = "piece `X` needs a 3 by 3 box and 4 states to use `t-spin`";
+        assert_eq!(
+            error(&format!("{HEADER}t-spin\nshape\n.#..\n###.\n....\n....\n")).1,
+            message
+        );
+        assert_eq!(
+            error(&format!("{HEADER}t-spin\nshape\n...\n###\n...\n")).1,
+            message
+        );
+    }
+}
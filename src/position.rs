@@ -1,6 +1,6 @@
 use std::os::raw::c_int;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     pub row: c_int,
     pub column: c_int,
@@ -0,0 +1,320 @@
+use std::fmt::Debug;
+
+use crate::rng::Rng;
+
+/// Decides which piece comes next. Pieces are identified by their index in
+/// the game's piece set.
+pub trait Randomizer: Debug {
+    /// Returns the index of the next piece, in `0..piece_count`.
+    fn next_piece(&mut self) -> usize;
+
+    /// Captures the randomizer's progress as plain numbers, for saving.
+    fn state(&self) -> Vec<u64>;
+
+    /// Continues from progress captured by [`Randomizer::state`]. Returns
+    /// false, leaving the randomizer as it was, if `state` could not have
+    /// come from this randomizer.
+    fn restore(&mut self, state: &[u64]) -> bool;
+}
+
+/// The randomizers a rule set can choose from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RandomizerKind {
+    /// Deals every piece once, in random order, before starting over.
+    Bag,
+    /// Like [`RandomizerKind::Bag`] with two copies of every piece.
+    DoubleBag,
+    /// Every piece equally likely every time.
+    Random,
+    /// TGM style: rerolls a piece found in the recent history, up to
+    /// `rerolls` times.
+    History { length: usize, rerolls: u32 },
+    /// Deals the given pieces in order, repeating once exhausted.
+    Sequence(Vec<usize>),
+}
+
+impl RandomizerKind {
+    pub fn build(&self, seed: u64, piece_count: usize) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::Bag => Box::new(BagRandomizer::new(seed, piece_count, 1)),
+            RandomizerKind::DoubleBag => Box::new(BagRandomizer::new(seed, piece_count, 2)),
+            RandomizerKind::Random => Box::new(PureRandomizer::new(seed, piece_count)),
+            RandomizerKind::History { length, rerolls } => {
+                Box::new(HistoryRandomizer::new(seed, piece_count, *length, *rerolls))
+            }
+            RandomizerKind::Sequence(pieces) => {
+                Box::new(SequenceRandomizer::new(pieces.clone(), piece_count))
+            }
+        }
+    }
+}
+
+/// Deals from a shuffled bag holding `copies` of every piece, refilling the
+/// bag once it is empty.
+#[derive(Debug, Clone)]
+pub struct BagRandomizer {
+    rng: Rng,
+    piece_count: usize,
+    copies: usize,
+    bag: Vec<usize>,
+}
+
+impl BagRandomizer {
+    pub fn new(seed: u64, piece_count: usize, copies: usize) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            piece_count,
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for BagRandomizer {
+    fn next_piece(&mut self) -> usize {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend(0..self.piece_count);
+            }
+        }
+
+        let random_index = self.rng.next_below(self.bag.len());
+        self.bag.remove(random_index)
+    }
+
+    fn state(&self) -> Vec<u64> {
+        let mut state = vec![self.rng.state()];
+        state.extend(self.bag.iter().map(|&piece| piece as u64));
+        state
+    }
+
+    fn restore(&mut self, state: &[u64]) -> bool {
+        let Some((&rng, bag)) = state.split_first() else {
+            return false;
+        };
+        if bag.len() > self.piece_count * self.copies
+            || bag.iter().any(|&piece| piece >= self.piece_count as u64)
+        {
+            return false;
+        }
+
+        self.rng = Rng::new(rng);
+        self.bag = bag.iter().map(|&piece| piece as usize).collect();
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PureRandomizer {
+    rng: Rng,
+    piece_count: usize,
+}
+
+impl PureRandomizer {
+    pub fn new(seed: u64, piece_count: usize) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            piece_count,
+        }
+    }
+}
+
+impl Randomizer for PureRandomizer {
+    fn next_piece(&mut self) -> usize {
+        self.rng.next_below(self.piece_count)
+    }
+
+    fn state(&self) -> Vec<u64> {
+        vec![self.rng.state()]
+    }
+
+    fn restore(&mut self, state: &[u64]) -> bool {
+        let &[rng] = state else {
+            return false;
+        };
+
+        self.rng = Rng::new(rng);
+        true
+    }
+}
+
+/// Rolls a random piece and rerolls while it is one of the last `length`
+/// pieces dealt, giving up after `rerolls` attempts.
+#[derive(Debug, Clone)]
+pub struct HistoryRandomizer {
+    rng: Rng,
+    piece_count: usize,
+    rerolls: u32,
+    history: Vec<usize>,
+    length: usize,
+}
+
+impl HistoryRandomizer {
+    pub fn new(seed: u64, piece_count: usize, length: usize, rerolls: u32) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            piece_count,
+            rerolls,
+            history: Vec::new(),
+            length,
+        }
+    }
+}
+
+impl Randomizer for HistoryRandomizer {
+    fn next_piece(&mut self) -> usize {
+        let mut piece = self.rng.next_below(self.piece_count);
+        for _ in 0..self.rerolls {
+            if !self.history.contains(&piece) {
+                break;
+            }
+            piece = self.rng.next_below(self.piece_count);
+        }
+
+        self.history.push(piece);
+        if self.history.len() > self.length {
+            self.history.remove(0);
+        }
+
+        piece
+    }
+
+    fn state(&self) -> Vec<u64> {
+        let mut state = vec![self.rng.state()];
+        state.extend(self.history.iter().map(|&piece| piece as u64));
+        state
+    }
+
+    fn restore(&mut self, state: &[u64]) -> bool {
+        let Some((&rng, history)) = state.split_first() else {
+            return false;
+        };
+        if history.len() > self.length
+            || history
+                .iter()
+                .any(|&piece| piece >= self.piece_count as u64)
+        {
+            return false;
+        }
+
+        self.rng = Rng::new(rng);
+        self.history = history.iter().map(|&piece| piece as usize).collect();
+        true
+    }
+}
+
+/// Deals a scripted sequence for puzzles and tests. Indices outside the
+/// piece set wrap around.
+#[derive(Debug, Clone)]
+pub struct SequenceRandomizer {
+    pieces: Vec<usize>,
+    piece_count: usize,
+    position: usize,
+}
+
+impl SequenceRandomizer {
+    pub fn new(pieces: Vec<usize>, piece_count: usize) -> Self {
+        Self {
+            pieces,
+            piece_count,
+            position: 0,
+        }
+    }
+}
+
+impl Randomizer for SequenceRandomizer {
+    fn next_piece(&mut self) -> usize {
+        if self.pieces.is_empty() {
+            return 0;
+        }
+
+        let piece = self.pieces[self.position % self.pieces.len()];
+        self.position += 1;
+
+        piece % self.piece_count.max(1)
+    }
+    fn state(&self) -> Vec<u64> {
+        vec![self.position as u64]
+    }
+
+    fn restore(&mut self, state: &[u64]) -> bool {
+        let &[position] = state else {
+            return false;
+        };
+
+        self.position = position as usize;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deal(randomizer: &mut dyn Randomizer, count: usize) -> Vec<usize> {
+        (0..count).map(|_| randomizer.next_piece()).collect()
+    }
+
+    #[test]
+    fn same_seed_deals_same_pieces() {
+        let kinds = [
+            RandomizerKind::Bag,
+            RandomizerKind::DoubleBag,
+            RandomizerKind::Random,
+            RandomizerKind::History {
+                length: 4,
+                rerolls: 4,
+            },
+        ];
+        for kind in kinds {
+            let mut a = kind.build(9, 7);
+            let mut b = kind.build(9, 7);
+            assert_eq!(deal(a.as_mut(), 50), deal(b.as_mut(), 50), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn bag_deals_every_piece_once() {
+        let mut bag = BagRandomizer::new(5, 7, 1);
+        for _ in 0..3 {
+            let mut pieces = deal(&mut bag, 7);
+            pieces.sort();
+            assert_eq!(pieces, (0..7).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn double_bag_deals_every_piece_twice() {
+        let mut bag = BagRandomizer::new(5, 7, 2);
+        let mut pieces = deal(&mut bag, 14);
+        pieces.sort();
+        let expected: Vec<_> = (0..7).flat_map(|piece| [piece, piece]).collect();
+        assert_eq!(pieces, expected);
+    }
+
+    #[test]
+    fn restore_continues_where_state_left_off() {
+        let mut bag = BagRandomizer::new(11, 7, 1);
+        deal(&mut bag, 3);
+        let mut copy = BagRandomizer::new(0, 7, 1);
+        assert!(copy.restore(&bag.state()));
+        assert_eq!(deal(&mut bag, 20), deal(&mut copy, 20));
+    }
+
+    #[test]
+    fn restore_rejects_foreign_state() {
+        let mut bag = BagRandomizer::new(0, 7, 1);
+        assert!(!bag.restore(&[]));
+        assert!(!bag.restore(&[1, 7]));
+        assert!(!bag.restore(&[1, 0, 1, 2, 3, 4, 5, 6, 0]));
+
+        let mut history = HistoryRandomizer::new(0, 7, 2, 4);
+        assert!(!history.restore(&[1, 0, 1, 2]));
+    }
+
+    #[test]
+    fn sequence_repeats_and_wraps() {
+        let mut sequence = SequenceRandomizer::new(vec![1, 9, 3], 7);
+        assert_eq!(deal(&mut sequence, 6), [1, 2, 3, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,572 @@
+use std::{ffi::CString, os::raw::c_int};
+
+use raylib::{
+    ffi::{
+        ClearBackground, Color, DrawRectangle, DrawRectangleRounded, DrawTextEx, Font, LoadFontEx,
+        MeasureTextEx, Rectangle, UnloadFont, Vector2,
+    },
+    prelude::Color as RaylibColor,
+};
+use tetris::{
+    block::Block,
+    clock::TICKS_PER_SECOND,
+    game::Game,
+    grid::Grid,
+    input::Input,
+    mode::Mode,
+    replay::{Playback, ReplayResult},
+};
+
+use crate::colors::{block_color, cell_color, DARK_BLUE, DARK_GREY, LIGHT_BLUE};
+
+pub const WINDOW_WIDTH: c_int = 690;
+pub const WINDOW_HEIGHT: c_int = 620;
+
+/// Screen area the visible part of the grid is fitted into.
+const GRID_X: c_int = 201;
+const GRID_Y: c_int = 11;
+const GRID_WIDTH: c_int = 300;
+const GRID_HEIGHT: c_int = 600;
+
+/// Cell size used for the pieces shown in the preview queue.
+const PREVIEW_CELL_SIZE: c_int = 24;
+
+/// Vertical space given to each piece in the preview queue.
+const PREVIEW_SLOT_HEIGHT: c_int = 60;
+
+/// Logic frames a clear label stays on screen.
+const LABEL_FRAMES: u64 = 120;
+
+/// Logic frames an input stays lit in the replay HUD after it is pressed,
+/// so taps that are released at once still show.
+const INPUT_FLASH_FRAMES: u64 = 8;
+
+/// How the replay HUD labels each input.
+const INPUT_LABELS: [(Input, &str); 7] = [
+    (Input::MoveLeft, "L"),
+    (Input::MoveRight, "R"),
+    (Input::SoftDrop, "SD"),
+    (Input::HardDrop, "HD"),
+    (Input::Rotate, "CW"),
+    (Input::RotateCounterClockwise, "CCW"),
+    (Input::Hold, "H"),
+];
+
+#[derive(Debug)]
+pub struct Renderer {
+    pub show_ghost: bool,
+    font: Font,
+    cell_size: c_int,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        let font_file_name = CString::new("assets/font/monogram.ttf").unwrap();
+
+        Self {
+            show_ghost: true,
+            font: unsafe { LoadFontEx(font_file_name.as_ptr(), 64, std::ptr::null_mut(), 0) },
+            cell_size: 30,
+        }
+    }
+
+    pub fn draw(&self, game: &Game) {
+        unsafe {
+            ClearBackground(DARK_BLUE);
+        }
+
+        self.draw_text("Hold", 60.0, 15.0);
+        self.draw_text("Score", 555.0, 15.0);
+        self.draw_text("Next", 560.0, 175.0);
+
+        unsafe {
+            DrawRectangleRounded(
+                Rectangle {
+                    x: 10.0,
+                    y: 55.0,
+                    width: 170.0,
+                    height: 180.0,
+                },
+                0.3,
+                6,
+                LIGHT_BLUE,
+            );
+        }
+
+        unsafe {
+            DrawRectangleRounded(
+                Rectangle {
+                    x: 510.0,
+                    y: 55.0,
+                    width: 170.0,
+                    height: 60.0,
+                },
+                0.3,
+                6,
+                LIGHT_BLUE,
+            );
+        }
+
+        let score_text = format!("{}", game.score);
+        let text_size = self.measure_text(&score_text);
+        self.draw_text(&score_text, 510.0 + (170.0 - text_size.x) / 2.0, 65.0);
+
+        unsafe {
+            DrawRectangleRounded(
+                Rectangle {
+                    x: 510.0,
+                    y: 215.0,
+                    width: 170.0,
+                    height: 360.0,
+                },
+                0.3,
+                6,
+                LIGHT_BLUE,
+            );
+        }
+
+        self.draw_stat("Level", &game.level.to_string(), 10.0, 255.0);
+        self.draw_stat("Lines", &game.lines.to_string(), 10.0, 375.0);
+
+        if let Some((frame, clear)) = game.last_clear() {
+            if game.frame() - frame < LABEL_FRAMES {
+                if let Some(label) = game.rules().scoring.label(clear) {
+                    self.draw_label(&label, 10.0, 495.0);
+                }
+            }
+        }
+
+        let layout = BoardLayout::new(game.grid());
+        self.draw_grid(game.grid(), game.pieces(), &layout);
+        if self.show_ghost {
+            let ghost_block = game.ghost_block();
+            let color = Color {
+                a: 70,
+                ..block_color(&ghost_block)
+            };
+            self.draw_block(&ghost_block, color, &layout);
+        }
+        let current_block = game.current_block();
+        self.draw_block(current_block, block_color(current_block), &layout);
+
+        for (slot, next_block) in game.next_blocks().enumerate() {
+            self.draw_block_centered(
+                next_block,
+                PREVIEW_CELL_SIZE,
+                PREVIEW_SLOT_HEIGHT - 12,
+                595,
+                215 + PREVIEW_SLOT_HEIGHT / 2 + slot as c_int * PREVIEW_SLOT_HEIGHT,
+            );
+        }
+
+        if let Some(held_block) = game.held_block() {
+            self.draw_block_centered(held_block, self.cell_size, 150, 95, 145);
+        }
+    }
+
+    /// Draws the title, offering to continue a saved game if `can_continue`.
+    pub fn draw_title(&self, can_continue: bool, message: Option<&str>) {
+        unsafe {
+            ClearBackground(DARK_BLUE);
+        }
+
+        self.draw_text_centered("TETRIS", 200.0, 96.0);
+        let mut options = vec!["Enter: New Game"];
+        if can_continue {
+            options.push("C: Continue");
+        }
+        options.push("R: Watch Last Replay");
+        for (index, option) in options.iter().enumerate() {
+            self.draw_text_centered(option, 380.0 + index as f32 * 50.0, 38.0);
+        }
+        if let Some(message) = message {
+            self.draw_text_centered(message, 540.0, 20.0);
+        }
+    }
+
+    pub fn draw_mode_select(&self, selected: usize) {
+        unsafe {
+            ClearBackground(DARK_BLUE);
+        }
+
+        self.draw_text_centered("Select Mode", 120.0, 56.0);
+        for (index, mode) in Mode::ALL.iter().enumerate() {
+            let text = if index == selected {
+                format!("> {} <", mode.name())
+            } else {
+                mode.name().to_string()
+            };
+            self.draw_text_centered(&text, 240.0 + index as f32 * 50.0, 38.0);
+        }
+    }
+
+    pub fn draw_countdown(&self, seconds: i32) {
+        self.draw_overlay();
+        self.draw_text_centered(&seconds.max(1).to_string(), 270.0, 96.0);
+    }
+
+    pub fn draw_paused(&self, saved: bool) {
+        self.draw_overlay();
+        self.draw_text_centered("PAUSED", 230.0, 56.0);
+        self.draw_text_centered("P: Resume", 320.0, 28.0);
+        self.draw_text_centered(if saved { "Saved" } else { "S: Save" }, 355.0, 28.0);
+        self.draw_text_centered("Q: Quit", 390.0, 28.0);
+    }
+
+    pub fn draw_results(&self, game: &Game) {
+        self.draw_overlay();
+        self.draw_text_centered("GAME OVER", 40.0, 56.0);
+        if let Some(top_out) = game.top_out() {
+            self.draw_text_centered(top_out.description(), 95.0, 28.0);
+        }
+
+        let statistics = &game.statistics;
+        let rows = [
+            format!("Score {}", game.score),
+            format!("Level {}", game.level),
+            format!("Lines {}", game.lines),
+            format!("Time {}", format_time(game.frame())),
+            format!("Pieces {}", statistics.pieces),
+            // "Tetrises", or "Pentrises" in Pentris.
+            format!(
+                "{}es {}",
+                game.rules().scoring.biggest_clear,
+                statistics.biggest_clears
+            ),
+            format!("T-Spins {}", statistics.t_spins),
+            format!("Perfect Clears {}", statistics.perfect_clears),
+            format!("Max Combo {}", statistics.max_combo),
+        ];
+        for (index, row) in rows.iter().enumerate() {
+            self.draw_text_centered(row, 140.0 + index as f32 * 40.0, 32.0);
+        }
+
+        self.draw_text_centered("Enter: Title", 540.0, 28.0);
+    }
+
+    /// Draws the replay viewer's HUD over the board: time and speed along
+    /// the top, and the recorded inputs lighting up along the bottom as they
+    /// are pressed.
+    pub fn draw_playback(&self, playback: &Playback, speed: f64, paused: bool) {
+        let game = playback.game();
+        let strip = Color {
+            a: 200,
+            ..DARK_BLUE
+        };
+        unsafe {
+            DrawRectangle(GRID_X, GRID_Y, GRID_WIDTH, 36, strip);
+            DrawRectangle(GRID_X, GRID_Y + GRID_HEIGHT - 36, GRID_WIDTH, 36, strip);
+        }
+
+        let status = if playback.is_finished() {
+            if ReplayResult::new(game) == playback.replay().result {
+                "End".to_string()
+            } else {
+                "Desync".to_string()
+            }
+        } else if paused {
+            "Paused".to_string()
+        } else {
+            format!("{speed}x")
+        };
+        let time = format!(
+            "{} / {}",
+            format_time(playback.frame()),
+            format_time(playback.end_frame())
+        );
+        self.draw_small_text(&time, GRID_X as f32 + 8.0, GRID_Y as f32 + 6.0);
+        let status_width = self.measure_small_text(&status).x;
+        self.draw_small_text(
+            &status,
+            (GRID_X + GRID_WIDTH) as f32 - 8.0 - status_width,
+            GRID_Y as f32 + 6.0,
+        );
+
+        let key_width = GRID_WIDTH / INPUT_LABELS.len() as c_int;
+        for (index, (input, label)) in INPUT_LABELS.into_iter().enumerate() {
+            let last_press = playback
+                .past_events()
+                .iter()
+                .rev()
+                .find(|event| event.input == input && event.pressed)
+                .map(|event| event.frame);
+            let active = game.held_inputs().contains(&input)
+                || last_press.is_some_and(|frame| game.frame() - frame < INPUT_FLASH_FRAMES);
+
+            let x = GRID_X + index as c_int * key_width;
+            let y = GRID_Y + GRID_HEIGHT - 32;
+            unsafe {
+                DrawRectangle(
+                    x + 2,
+                    y,
+                    key_width - 4,
+                    28,
+                    if active { LIGHT_BLUE } else { DARK_GREY },
+                );
+            }
+            let label_width = self.measure_small_text(label).x;
+            self.draw_small_text(
+                label,
+                x as f32 + (key_width as f32 - label_width) / 2.0,
+                y as f32 + 2.0,
+            );
+        }
+
+        // Controls go over the clear label, under the level and lines.
+        if paused {
+            unsafe {
+                DrawRectangle(0, 490, GRID_X - 1, WINDOW_HEIGHT - 490, DARK_BLUE);
+            }
+            let controls = [
+                "Space: Play",
+                "Up/Down: Speed",
+                "Left/Right: Seek",
+                ",/.: Step",
+                "Esc: Exit",
+            ];
+            for (index, control) in controls.iter().enumerate() {
+                self.draw_small_text(control, 10.0, 495.0 + index as f32 * 23.0);
+            }
+        }
+    }
+
+    /// Dims everything drawn so far.
+    fn draw_overlay(&self) {
+        unsafe {
+            DrawRectangle(
+                0,
+                0,
+                WINDOW_WIDTH,
+                WINDOW_HEIGHT,
+                Color {
+                    a: 200,
+                    ..DARK_BLUE
+                },
+            );
+        }
+    }
+
+    /// Draws a titled panel holding a single centred value, 170 px wide.
+    fn draw_stat(&self, title: &str, value: &str, x: f32, y: f32) {
+        let title_size = self.measure_text(title);
+        self.draw_text(title, x + (170.0 - title_size.x) / 2.0, y);
+
+        unsafe {
+            DrawRectangleRounded(
+                Rectangle {
+                    x,
+                    y: y + 40.0,
+                    width: 170.0,
+                    height: 60.0,
+                },
+                0.3,
+                6,
+                LIGHT_BLUE,
+            );
+        }
+
+        let value_size = self.measure_text(value);
+        self.draw_text(value, x + (170.0 - value_size.x) / 2.0, y + 50.0);
+    }
+
+    /// Draws the visible rows of the grid; the hidden spawn rows are left
+    /// off screen.
+    fn draw_grid(&self, grid: &Grid, pieces: &[Block], layout: &BoardLayout) {
+        for row in grid.hidden_rows..grid.num_rows {
+            for column in 0..grid.num_cols {
+                let cell_value = grid.cell(row, column);
+                let (x, y) = layout.cell_position(row, column);
+                draw_cell(x, y, layout.cell_size, cell_color(pieces, cell_value));
+            }
+        }
+    }
+
+    /// Draws the cells of a block on the board, skipping any still in the
+    /// hidden rows.
+    fn draw_block(&self, block: &Block, color: Color, layout: &BoardLayout) {
+        for item in block.get_cell_positions() {
+            if item.row >= layout.hidden_rows {
+                let (x, y) = layout.cell_position(item.row, item.column);
+                draw_cell(x, y, layout.cell_size, color);
+            }
+        }
+    }
+
+    /// Draws `block` with its bounding box centred on `(center_x, center_y)`,
+    /// shrinking the cells if the block would be taller than `max_height`.
+    fn draw_block_centered(
+        &self,
+        block: &Block,
+        cell_size: c_int,
+        max_height: c_int,
+        center_x: c_int,
+        center_y: c_int,
+    ) {
+        let tiles = block.get_cell_positions();
+        let min_row = tiles.iter().map(|item| item.row).min().unwrap_or_default();
+        let max_row = tiles.iter().map(|item| item.row).max().unwrap_or_default();
+        let min_column = tiles
+            .iter()
+            .map(|item| item.column)
+            .min()
+            .unwrap_or_default();
+        let max_column = tiles
+            .iter()
+            .map(|item| item.column)
+            .max()
+            .unwrap_or_default();
+        let cell_size = cell_size.min(max_height / (max_row - min_row + 1));
+
+        let origin_x = center_x - (max_column - min_column + 1) * cell_size / 2;
+        let origin_y = center_y - (max_row - min_row + 1) * cell_size / 2;
+
+        for item in tiles {
+            draw_cell(
+                origin_x + (item.column - min_column) * cell_size,
+                origin_y + (item.row - min_row) * cell_size,
+                cell_size,
+                block_color(block),
+            );
+        }
+    }
+
+    fn draw_text(&self, text: &str, x: f32, y: f32) {
+        let text = CString::new(text).unwrap();
+        unsafe {
+            DrawTextEx(
+                self.font,
+                text.as_ptr(),
+                Vector2 { x, y },
+                38.0,
+                2.0,
+                RaylibColor::WHITE.into(),
+            );
+        }
+    }
+
+    /// Draws `label` in a smaller font, wrapped onto one line per word and
+    /// centred in a 170 px column.
+    fn draw_label(&self, label: &str, x: f32, y: f32) {
+        let mut lines: Vec<String> = Vec::new();
+        for word in label.split(' ') {
+            match lines.last_mut() {
+                Some(line) if line.len() + word.len() < 10 => {
+                    line.push(' ');
+                    line.push_str(word);
+                }
+                _ => lines.push(word.to_string()),
+            }
+        }
+
+        for (index, line) in lines.iter().enumerate() {
+            let text = CString::new(line.as_str()).unwrap();
+            unsafe {
+                let size = MeasureTextEx(self.font, text.as_ptr(), 28.0, 2.0);
+                DrawTextEx(
+                    self.font,
+                    text.as_ptr(),
+                    Vector2 {
+                        x: x + (170.0 - size.x) / 2.0,
+                        y: y + index as f32 * 30.0,
+                    },
+                    28.0,
+                    2.0,
+                    RaylibColor::WHITE.into(),
+                );
+            }
+        }
+    }
+
+    /// Draws `text` centred horizontally in the window.
+    fn draw_text_centered(&self, text: &str, y: f32, font_size: f32) {
+        let text = CString::new(text).unwrap();
+        unsafe {
+            let size = MeasureTextEx(self.font, text.as_ptr(), font_size, 2.0);
+            DrawTextEx(
+                self.font,
+                text.as_ptr(),
+                Vector2 {
+                    x: (WINDOW_WIDTH as f32 - size.x) / 2.0,
+                    y,
+                },
+                font_size,
+                2.0,
+                RaylibColor::WHITE.into(),
+            );
+        }
+    }
+
+    fn draw_small_text(&self, text: &str, x: f32, y: f32) {
+        let text = CString::new(text).unwrap();
+        unsafe {
+            DrawTextEx(
+                self.font,
+                text.as_ptr(),
+                Vector2 { x, y },
+                24.0,
+                2.0,
+                RaylibColor::WHITE.into(),
+            );
+        }
+    }
+
+    fn measure_small_text(&self, text: &str) -> Vector2 {
+        let text = CString::new(text).unwrap();
+        unsafe { MeasureTextEx(self.font, text.as_ptr(), 24.0, 2.0) }
+    }
+
+    fn measure_text(&self, text: &str) -> Vector2 {
+        let text = CString::new(text).unwrap();
+        unsafe { MeasureTextEx(self.font, text.as_ptr(), 38.0, 2.0) }
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        unsafe {
+            UnloadFont(self.font);
+        }
+    }
+}
+
+/// Where the visible rows of a grid land on screen: scaled to fit the grid
+/// area and centred in it.
+#[derive(Debug)]
+struct BoardLayout {
+    x: c_int,
+    y: c_int,
+    cell_size: c_int,
+    hidden_rows: c_int,
+}
+
+impl BoardLayout {
+    fn new(grid: &Grid) -> Self {
+        let cell_size = (GRID_WIDTH / grid.num_cols).min(GRID_HEIGHT / grid.visible_rows());
+
+        Self {
+            x: GRID_X + (GRID_WIDTH - grid.num_cols * cell_size) / 2,
+            y: GRID_Y + (GRID_HEIGHT - grid.visible_rows() * cell_size) / 2,
+            cell_size,
+            hidden_rows: grid.hidden_rows,
+        }
+    }
+
+    fn cell_position(&self, row: c_int, column: c_int) -> (c_int, c_int) {
+        (
+            self.x + column * self.cell_size,
+            self.y + (row - self.hidden_rows) * self.cell_size,
+        )
+    }
+}
+
+/// Formats a frame count as minutes and seconds of play.
+fn format_time(frames: u64) -> String {
+    let seconds = frames / TICKS_PER_SECOND as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn draw_cell(x: c_int, y: c_int, cell_size: c_int, color: Color) {
+    unsafe {
+        DrawRectangle(x, y, cell_size - 1, cell_size - 1, color);
+    }
+}
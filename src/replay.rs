@@ -0,0 +1,602 @@
+use std::{fmt::Write, fs, io, os::raw::c_int, path::Path};
+
+use crate::{
+    block::Block,
+    clock::ManualTimeSource,
+    game::{Game, GameEvent, GameState},
+    grid::MAX_COLUMNS,
+    handling::Handling,
+    input::{Input, InputEvent},
+    mode::Mode,
+    pieces,
+    randomizer::RandomizerKind,
+    rules::{
+        Rules, MAX_BOARD_HEIGHT, MAX_LOCK_DELAY, MAX_LOCK_RESETS, MAX_PREVIEW_COUNT,
+        MAX_START_LEVEL,
+    },
+    text::{join, Field, Fields, Invalid, TextError},
+};
+
+/// Version written to new replays. Replays of any other version are
+/// rejected.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Frames between the snapshots a [`Playback`] seeks from.
+pub const SNAPSHOT_INTERVAL: u64 = 300;
+
+/// First word of every replay file.
+const MAGIC: &str = "tetris-replay";
+
+/// How each input is written in the event log.
+const INPUT_CODES: [(Input, &str); 7] = [
+    (Input::MoveLeft, "left"),
+    (Input::MoveRight, "right"),
+    (Input::SoftDrop, "soft"),
+    (Input::HardDrop, "hard"),
+    (Input::Rotate, "cw"),
+    (Input::RotateCounterClockwise, "ccw"),
+    (Input::Hold, "hold"),
+];
+
+/// Where a recorded game ended, for checking that playback matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayResult {
+    pub frames: u64,
+    pub score: c_int,
+    pub lines: c_int,
+    /// [`Game::state_hash`] at the end of the game.
+    pub hash: u64,
+}
+
+impl ReplayResult {
+    pub fn new(game: &Game) -> Self {
+        Self {
+            frames: game.frame(),
+            score: game.score,
+            lines: game.lines,
+            hash: game.state_hash(),
+        }
+    }
+}
+
+/// A recorded game: how it was set up and every input it received.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub mode: Mode,
+    pub seed: u64,
+    /// The rules played under. Only the settings are recorded; gravity and
+    /// scoring tables come from the mode, and the piece set has to be
+    /// supplied through [`Replay::set_pieces`].
+    pub rules: Rules,
+    /// [`pieces::fingerprint`] of the piece set the game was played with.
+    pub pieces: u64,
+    pub handling: Handling,
+    pub events: Vec<InputEvent>,
+    pub result: ReplayResult,
+}
+
+impl Replay {
+    /// Records `game`, played in `mode`, as it stands now.
+    pub fn record(mode: Mode, game: &Game) -> Self {
+        Self {
+            mode,
+            seed: game.seed(),
+            rules: game.rules().clone(),
+            pieces: pieces::fingerprint(game.pieces()),
+            handling: game.handling(),
+            events: game.input_log().to_vec(),
+            result: ReplayResult::new(game),
+        }
+    }
+
+    /// Plays the replay back with `pieces`, which must be the piece set it
+    /// was recorded with.
+    pub fn set_pieces(&mut self, pieces: Vec<Block>) -> Result<(), &'static str> {
+        if pieces::fingerprint(&pieces) != self.pieces {
+            return Err("replay was recorded with a different piece set");
+        }
+
+        self.rules.pieces = pieces;
+        Ok(())
+    }
+
+    /// Checks that the replay was played under its mode's rule settings,
+    /// with handling a player may choose, as a leaderboard requires.
+    /// Returns which setting differs if not.
+    pub fn check_rules(&self) -> Result<(), String> {
+        let rules = &self.rules;
+        let mut expected = self.mode.rules();
+        expected.clamp();
+
+        let differences = [
+            ("board width", rules.board_width != expected.board_width),
+            ("board height", rules.board_height != expected.board_height),
+            ("hidden rows", rules.hidden_rows != expected.hidden_rows),
+            ("preview", rules.preview_count != expected.preview_count),
+            ("start level", rules.start_level != expected.start_level),
+            ("lock delay", rules.lock_delay != expected.lock_delay),
+            (
+                "lock resets",
+                rules.max_lock_resets != expected.max_lock_resets,
+            ),
+            (
+                "top out",
+                rules.partial_lock_out != expected.partial_lock_out
+                    || rules.garbage_top_out != expected.garbage_top_out,
+            ),
+            ("randomizer", rules.randomizer != expected.randomizer),
+        ];
+        if let Some((setting, _)) = differences.iter().find(|(_, differs)| *differs) {
+            return Err(format!("{setting} differs from {} rules", self.mode.name()));
+        }
+        if !self.handling.is_valid() {
+            return Err("handling is out of range".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// A new game set up as the recorded one was, before any input.
+    pub fn start(&self) -> Game {
+        let mut game = Game::with_time_source(
+            self.seed,
+            self.rules.clone(),
+            Box::new(ManualTimeSource::new()),
+        );
+        game.set_handling(self.handling);
+
+        game
+    }
+
+    /// Feeds `game` the recorded events due before its next frame, starting
+    /// at `events[*next]`, and moves `next` past them.
+    pub fn apply_events(&self, game: &mut Game, next: &mut usize) {
+        while let Some(event) = self.events.get(*next) {
+            if event.frame > game.frame() {
+                break;
+            }
+
+            if event.pressed {
+                game.handle_input(event.input);
+            } else {
+                game.release_input(event.input);
+            }
+            *next += 1;
+        }
+    }
+
+    /// Plays the whole replay without a window and returns the game as it
+    /// ended.
+    pub fn simulate(&self) -> Game {
+        let mut game = self.start();
+        let mut next = 0;
+        loop {
+            self.apply_events(&mut game, &mut next);
+            if game.frame() >= self.result.frames || game.game_over {
+                break;
+            }
+            game.tick();
+        }
+
+        game
+    }
+}
+
+/// A replay being watched one frame at a time. Snapshots of the game are
+/// kept every [`SNAPSHOT_INTERVAL`] frames as playback passes them, so
+/// seeking back only replays the frames since the nearest one.
+#[derive(Debug)]
+pub struct Playback {
+    replay: Replay,
+    game: Game,
+    /// Index in the replay's events of the next one to apply.
+    next_event: usize,
+    /// The game at every multiple of [`SNAPSHOT_INTERVAL`] reached so far,
+    /// with the index of the next event to apply from there.
+    snapshots: Vec<(GameState, usize)>,
+}
+
+impl Playback {
+    /// Starts watching `replay` from its first frame.
+    pub fn new(replay: Replay) -> Self {
+        let mut game = replay.start();
+        let mut next_event = 0;
+        replay.apply_events(&mut game, &mut next_event);
+
+        let mut playback = Self {
+            replay,
+            game,
+            next_event,
+            snapshots: Vec::new(),
+        };
+        playback.take_snapshot();
+
+        playback
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.game.frame()
+    }
+
+    /// The frame the recorded game ended on.
+    pub fn end_frame(&self) -> u64 {
+        self.replay.result.frames
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.game.frame() >= self.end_frame() || self.game.game_over
+    }
+
+    /// The recorded events applied so far, oldest first.
+    pub fn past_events(&self) -> &[InputEvent] {
+        &self.replay.events[..self.next_event]
+    }
+
+    /// Returns the game events produced since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        self.game.take_events()
+    }
+
+    /// Advances one frame and applies the inputs recorded on it. Does
+    /// nothing once the replay has finished.
+    pub fn step(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+
+        self.game.tick();
+        self.replay
+            .apply_events(&mut self.game, &mut self.next_event);
+        self.take_snapshot();
+    }
+
+    /// Moves playback to `frame`, or to the end if the replay is shorter.
+    /// Game events produced on the way are dropped.
+    pub fn seek(&mut self, frame: u64) {
+        let frame = frame.min(self.end_frame());
+        let index = ((frame / SNAPSHOT_INTERVAL) as usize).min(self.snapshots.len() - 1);
+        let (state, next_event) = &self.snapshots[index];
+        if frame < self.game.frame() || state.frame > self.game.frame() {
+            let mut game = Game::restore_with_time_source(
+                self.replay.rules.clone(),
+                state,
+                Box::new(ManualTimeSource::new()),
+            )
+            .expect("snapshot is taken from the same rules");
+            game.set_handling(self.replay.handling);
+            self.game = game;
+            self.next_event = *next_event;
+        }
+
+        while self.game.frame() < frame && !self.is_finished() {
+            self.step();
+        }
+        self.game.take_events();
+    }
+
+    fn take_snapshot(&mut self) {
+        let frame = self.game.frame();
+        if frame != self.snapshots.len() as u64 * SNAPSHOT_INTERVAL {
+            return;
+        }
+
+        let mut state = self.game.state();
+        // The replay already holds the inputs; keeping a growing copy in
+        // every snapshot would only cost memory.
+        state.input_log.clear();
+        self.snapshots.push((state, self.next_event));
+    }
+}
+
+pub fn write(path: impl AsRef<Path>, replay: &Replay) -> io::Result<()> {
+    fs::write(path, to_string(replay))
+}
+
+pub fn read(path: impl AsRef<Path>) -> Result<Replay, TextError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Writes a replay as text: the settings, the result, then one line per
+/// input event with the frames since the previous event and `+` for a
+/// press or `-` for a release.
+pub fn to_string(replay: &Replay) -> String {
+    let rules = &replay.rules;
+    let handling = &replay.handling;
+    let result = &replay.result;
+    let mut text = format!("{MAGIC} {REPLAY_VERSION}\n");
+    let mut line = |key: &str, values: String| {
+        writeln!(text, "{key} {values}").unwrap();
+    };
+
+    line("mode", replay.mode.name().to_string());
+    line("seed", replay.seed.to_string());
+    line(
+        "board",
+        format!(
+            "{} {} {}",
+            rules.board_width, rules.board_height, rules.hidden_rows
+        ),
+    );
+    line("preview", rules.preview_count.to_string());
+    line("start-level", rules.start_level.to_string());
+    line(
+        "lock",
+        format!("{:?} {}", rules.lock_delay, rules.max_lock_resets),
+    );
+    line(
+        "top-out",
+        format!("{} {}", rules.partial_lock_out, rules.garbage_top_out),
+    );
+    line("randomizer", randomizer_to_string(&rules.randomizer));
+    line("pieces", format!("{:016x}", replay.pieces));
+    line(
+        "handling",
+        format!(
+            "{} {} {}",
+            handling.das, handling.arr, handling.soft_drop_interval
+        ),
+    );
+    line(
+        "result",
+        format!(
+            "{} {} {} {:016x}",
+            result.frames, result.score, result.lines, result.hash
+        ),
+    );
+
+    let mut last_frame = 0;
+    for event in &replay.events {
+        let sign = if event.pressed { '+' } else { '-' };
+        line(
+            "input",
+            format!(
+                "{} {sign}{}",
+                event.frame - last_frame,
+                input_code(event.input)
+            ),
+        );
+        last_frame = event.frame;
+    }
+
+    text
+}
+
+/// Reads a replay written by [`to_string`]. Its rules start out with the
+/// mode's built-in piece set; call [`Replay::set_pieces`] to check and
+/// replace it before playing the replay back.
+pub fn parse(text: &str) -> Result<Replay, TextError> {
+    let fields = Fields::parse(text, MAGIC, REPLAY_VERSION)?;
+
+    let mode_name = fields.one("mode")?;
+    let mode = Mode::ALL
+        .into_iter()
+        .find(|mode| [mode.name()] == mode_name.values[..])
+        .ok_or_else(|| mode_name.error("unknown mode"))?;
+
+    let board = fields.one("board")?;
+    let [board_width, board_height, hidden_rows] = board.exactly()?;
+    let lock = fields.one("lock")?;
+    let [lock_delay, max_lock_resets] = lock.exactly()?;
+    let top_out = fields.one("top-out")?;
+    let [partial_lock_out, garbage_top_out] = top_out.exactly()?;
+    let preview = fields.one("preview")?;
+    let [preview_count] = preview.exactly()?;
+    let start_level = fields.one("start-level")?;
+    let [start] = start_level.exactly()?;
+    let rules = Rules {
+        board_width: board.ranged(board_width, 1..=MAX_COLUMNS)?,
+        board_height: board.ranged(board_height, 1..=MAX_BOARD_HEIGHT)?,
+        hidden_rows: board.ranged(hidden_rows, 2..=MAX_BOARD_HEIGHT)?,
+        preview_count: preview.ranged(preview_count, 0..=MAX_PREVIEW_COUNT)?,
+        start_level: start_level.ranged(start, 1..=MAX_START_LEVEL)?,
+        lock_delay: lock.ranged(lock_delay, 0.0..=MAX_LOCK_DELAY)?,
+        max_lock_resets: lock.ranged(max_lock_resets, 0..=MAX_LOCK_RESETS)?,
+        partial_lock_out: top_out.value(partial_lock_out)?,
+        garbage_top_out: top_out.value(garbage_top_out)?,
+        randomizer: parse_randomizer(&fields.one("randomizer")?)?,
+        ..mode.rules()
+    };
+
+    let handling = fields.one("handling")?;
+    let [das, arr, soft_drop_interval] = handling.exactly()?;
+    let handling = Handling {
+        das: handling.value(das)?,
+        arr: handling.value(arr)?,
+        soft_drop_interval: handling.value(soft_drop_interval)?,
+    };
+
+    let result = fields.one("result")?;
+    let [frames, score, lines, hash] = result.exactly()?;
+    let result = ReplayResult {
+        frames: result.value(frames)?,
+        score: result.value(score)?,
+        lines: result.value(lines)?,
+        hash: parse_hash(&result, hash)?,
+    };
+
+    let mut frame: u64 = 0;
+    let mut events = Vec::new();
+    for event in fields.all("input") {
+        let [delay, code] = event.exactly()?;
+        frame = frame
+            .checked_add(event.value(delay)?)
+            .ok_or_else(|| event.error("frame is out of range"))?;
+
+        let (pressed, name) = match code.split_at_checked(1) {
+            Some(("+", name)) => (true, name),
+            Some(("-", name)) => (false, name),
+            _ => return Err(event.error("expected `+` or `-` before the input").into()),
+        };
+        let input = INPUT_CODES
+            .iter()
+            .find(|(_, code)| *code == name)
+            .map(|&(input, _)| input)
+            .ok_or_else(|| event.error(&format!("unknown input `{name}`")))?;
+
+        events.push(InputEvent {
+            frame,
+            input,
+            pressed,
+        });
+    }
+
+    let pieces = fields.one("pieces")?;
+    let [fingerprint] = pieces.exactly()?;
+    Ok(Replay {
+        mode,
+        seed: fields.one("seed")?.single()?,
+        rules,
+        pieces: parse_hash(&pieces, fingerprint)?,
+        handling,
+        events,
+        result,
+    })
+}
+
+fn parse_hash(field: &Field, hash: &str) -> Result<u64, Invalid> {
+    u64::from_str_radix(hash, 16).map_err(|_| field.error(&format!("`{hash}` is not a valid hash")))
+}
+
+fn input_code(input: Input) -> &'static str {
+    INPUT_CODES
+        .iter()
+        .find(|&&(entry, _)| entry == input)
+        .map(|&(_, code)| code)
+        .unwrap()
+}
+
+fn randomizer_to_string(randomizer: &RandomizerKind) -> String {
+    match randomizer {
+        RandomizerKind::Bag => "bag".to_string(),
+        RandomizerKind::DoubleBag => "double-bag".to_string(),
+        RandomizerKind::Random => "random".to_string(),
+        RandomizerKind::History { length, rerolls } => format!("history {length} {rerolls}"),
+        RandomizerKind::Sequence(pieces) => format!("sequence {}", join(pieces.iter())),
+    }
+}
+
+fn parse_randomizer(field: &Field) -> Result<RandomizerKind, Invalid> {
+    match field.values[..] {
+        ["bag"] => Ok(RandomizerKind::Bag),
+        ["double-bag"] => Ok(RandomizerKind::DoubleBag),
+        ["random"] => Ok(RandomizerKind::Random),
+        ["history", length, rerolls] => Ok(RandomizerKind::History {
+            length: field.value(length)?,
+            rerolls: field.value(rerolls)?,
+        }),
+        ["sequence", ref pieces @ ..] => Ok(RandomizerKind::Sequence(
+            pieces
+                .iter()
+                .map(|piece| field.value(piece))
+                .collect::<Result<_, _>>()?,
+        )),
+        _ => Err(field.error("unknown randomizer")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A recorded Marathon game long enough to pass a few snapshots.
+    fn recorded() -> Replay {
+        let mut game =
+            Game::with_time_source(3, Mode::Marathon.rules(), Box::new(ManualTimeSource::new()));
+        for (index, input) in Input::ALL.iter().cycle().take(120).enumerate() {
+            game.handle_input(*input);
+            for _ in 0..10 + index % 9 {
+                game.tick();
+            }
+            game.release_input(*input);
+        }
+
+        Replay::record(Mode::Marathon, &game)
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = recorded();
+        let text = to_string(&replay);
+        let parsed = parse(&text).unwrap();
+        assert_eq!(to_string(&parsed), text);
+        assert_eq!(parsed.events, replay.events);
+        assert_eq!(parsed.result, replay.result);
+    }
+
+    #[test]
+    fn simulate_reproduces_the_game() {
+        let replay = recorded();
+        assert!(replay.result.frames > 3 * SNAPSHOT_INTERVAL);
+        assert_eq!(replay.check_rules(), Ok(()));
+
+        let mut parsed = parse(&to_string(&replay)).unwrap();
+        parsed.set_pieces(pieces::standard()).unwrap();
+        assert_eq!(ReplayResult::new(&parsed.simulate()), replay.result);
+    }
+
+    #[test]
+    fn seeking_back_matches_playing_forward() {
+        let replay = recorded();
+        let mut playback = Playback::new(replay.clone());
+        playback.seek(playback.end_frame());
+        assert_eq!(playback.game().state_hash(), replay.result.hash);
+
+        playback.seek(2 * SNAPSHOT_INTERVAL + 17);
+        let mut fresh = Playback::new(replay);
+        fresh.seek(2 * SNAPSHOT_INTERVAL + 17);
+        assert_eq!(playback.game().state_hash(), fresh.game().state_hash());
+    }
+
+    #[test]
+    fn inputs_after_top_out_are_ignored() {
+        let mut game =
+            Game::with_time_source(3, Mode::Marathon.rules(), Box::new(ManualTimeSource::new()));
+        while !game.game_over {
+            game.handle_input(Input::HardDrop);
+        }
+        game.handle_input(Input::MoveLeft);
+        game.tick();
+
+        let replay = Replay::record(Mode::Marathon, &game);
+        assert_eq!(ReplayResult::new(&replay.simulate()), replay.result);
+    }
+
+    #[test]
+    fn rejects_a_different_piece_set() {
+        let mut replay = recorded();
+        assert!(replay.set_pieces(pieces::pentominoes()).is_err());
+    }
+
+    #[test]
+    fn rejects_settings_out_of_range() {
+        let text = to_string(&recorded());
+        for (setting, bad) in [
+            ("board 10 20 20", "board 10 -30 20"),
+            ("lock 0.5 15", "lock NaN 15"),
+            ("start-level 1", "start-level 100000"),
+        ] {
+            assert!(text.contains(setting), "{setting}");
+            let result = parse(&text.replace(setting, bad));
+            assert!(matches!(result, Err(TextError::Invalid { .. })), "{bad}");
+        }
+    }
+
+    #[test]
+    fn check_rules_catches_changed_settings() {
+        let mut replay = recorded();
+        replay.rules.preview_count = 1;
+        assert!(replay.check_rules().is_err());
+
+        let mut replay = recorded();
+        replay.handling.soft_drop_interval = 0;
+        assert!(replay.check_rules().is_err());
+    }
+}
@@ -0,0 +1,68 @@
+/// A small seedable pseudo-random number generator (SplitMix64).
+///
+/// The same seed always yields the same sequence, on every platform.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// The generator's position in its sequence. `Rng::new(rng.state())`
+    /// continues exactly where `rng` is.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..bound`.
+    pub fn next_below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        let mut a = Rng::new(1);
+        let mut b = Rng::new(2);
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn state_continues_the_sequence() {
+        let mut rng = Rng::new(7);
+        rng.next_u64();
+        let mut copy = Rng::new(rng.state());
+        assert_eq!(rng.next_u64(), copy.next_u64());
+    }
+
+    #[test]
+    fn next_below_stays_in_bounds() {
+        let mut rng = Rng::new(3);
+        assert!((0..1000).all(|_| rng.next_below(7) < 7));
+    }
+}
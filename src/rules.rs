@@ -0,0 +1,114 @@
+use std::os::raw::c_int;
+
+use crate::{
+    block::Block, grid::MAX_COLUMNS, pieces, randomizer::RandomizerKind, scoring::ScoreTable,
+};
+
+/// The largest number of upcoming pieces the game will preview.
+pub const MAX_PREVIEW_COUNT: usize = 6;
+
+/// The most rows a game allows in the visible field, and in the hidden
+/// buffer above it.
+pub const MAX_BOARD_HEIGHT: c_int = 1000;
+
+/// The highest level a game may start on.
+pub const MAX_START_LEVEL: c_int = 99;
+
+/// The longest lock delay a game allows, in seconds.
+pub const MAX_LOCK_DELAY: f64 = 60.0;
+
+/// The most lock delay resets a game allows per piece.
+pub const MAX_LOCK_RESETS: u32 = 1000;
+
+/// Cleared lines needed to advance one level.
+pub const LINES_PER_LEVEL: c_int = 10;
+
+/// Settings fixed for the lifetime of a game.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Columns of the board, from 1 to [`MAX_COLUMNS`].
+    pub board_width: c_int,
+    /// Rows of the visible field, from 1 to [`MAX_BOARD_HEIGHT`].
+    pub board_height: c_int,
+    /// Rows of buffer above the visible field where pieces spawn. At
+    /// least two are always kept, and at most [`MAX_BOARD_HEIGHT`].
+    pub hidden_rows: c_int,
+    /// How many upcoming pieces are shown, from 0 to [`MAX_PREVIEW_COUNT`].
+    pub preview_count: usize,
+    /// Level the game starts on, from 1 to [`MAX_START_LEVEL`].
+    pub start_level: c_int,
+    /// Seconds the piece takes to fall one row, indexed by level starting
+    /// at level 1. Levels past the end of the table reuse its last entry.
+    pub gravity: Vec<f64>,
+    /// Seconds a piece may rest on the stack before it locks, up to
+    /// [`MAX_LOCK_DELAY`].
+    pub lock_delay: f64,
+    /// How many moves or rotations may restart the lock delay before the
+    /// piece locks as soon as it touches down, up to [`MAX_LOCK_RESETS`].
+    pub max_lock_resets: u32,
+    pub scoring: ScoreTable,
+    /// End the game when a piece locks partly above the visible field,
+    /// not only when it locks entirely above it.
+    pub partial_lock_out: bool,
+    /// End the game when garbage pushes blocks out of the top of the board.
+    /// Otherwise those blocks are discarded.
+    pub garbage_top_out: bool,
+    /// Every piece that can be dealt. Ids must run from 1 in order.
+    pub pieces: Vec<Block>,
+    /// How the piece sequence is generated.
+    pub randomizer: RandomizerKind,
+}
+
+impl Rules {
+    /// Brings every setting into the range a game allows.
+    pub fn clamp(&mut self) {
+        self.preview_count = self.preview_count.min(MAX_PREVIEW_COUNT);
+        self.hidden_rows = self.hidden_rows.clamp(2, MAX_BOARD_HEIGHT);
+        self.board_width = self.board_width.clamp(1, MAX_COLUMNS);
+        self.board_height = self.board_height.clamp(1, MAX_BOARD_HEIGHT);
+        self.start_level = self.start_level.clamp(1, MAX_START_LEVEL);
+        self.lock_delay = self.lock_delay.clamp(0.0, MAX_LOCK_DELAY);
+        self.max_lock_resets = self.max_lock_resets.min(MAX_LOCK_RESETS);
+    }
+
+    /// Seconds per row at `level`, looked up in the gravity table.
+    pub fn gravity_at(&self, level: c_int) -> f64 {
+        let index = (level.max(1) - 1) as usize;
+
+        match self.gravity.get(index).or(self.gravity.last()) {
+            Some(&seconds) => seconds,
+            None => 1.0,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            board_width: 10,
+            board_height: 20,
+            hidden_rows: 20,
+            preview_count: 5,
+            start_level: 1,
+            gravity: guideline_gravity(),
+            lock_delay: 0.5,
+            max_lock_resets: 15,
+            scoring: ScoreTable::default(),
+            partial_lock_out: false,
+            garbage_top_out: true,
+            pieces: pieces::standard(),
+            randomizer: RandomizerKind::Bag,
+        }
+    }
+}
+
+/// The guideline speed curve, `(0.8 - (level - 1) * 0.007) ^ (level - 1)`
+/// seconds per row, for levels 1 to 20.
+pub fn guideline_gravity() -> Vec<f64> {
+    (1..=20)
+        .map(|level| {
+            let level = level as f64;
+            (0.8 - (level - 1.0) * 0.007).powf(level - 1.0)
+        })
+        .collect()
+}
@@ -0,0 +1,336 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use crate::{
+    game::{BlockState, GameState, TopOut},
+    handling::{Handling, MAX_FRAMES},
+    input::{Input, InputEvent},
+    mode::Mode,
+    scoring::{LineClear, ScoreState, Spin},
+    statistics::Statistics,
+    text::{join, optional, Field, Fields, Invalid, TextError},
+};
+
+/// Version written to new saves. Saves of any other version are rejected.
+pub const SAVE_VERSION: u32 = 1;
+
+/// First word of every save file.
+const MAGIC: &str = "tetris-save";
+
+const TOP_OUTS: [TopOut; 4] = [
+    TopOut::BlockOut,
+    TopOut::LockOut,
+    TopOut::PartialLockOut,
+    TopOut::GarbageOut,
+];
+
+const SPINS: [Spin; 3] = [Spin::None, Spin::Mini, Spin::Full];
+
+/// A game in progress, the mode it is played in and the handling it was
+/// played with, which it keeps when continued so its replay stays valid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedGame {
+    pub mode: Mode,
+    pub handling: Handling,
+    pub state: GameState,
+}
+
+pub fn write(path: impl AsRef<Path>, saved: &SavedGame) -> io::Result<()> {
+    fs::write(path, to_string(saved))
+}
+
+pub fn read(path: impl AsRef<Path>) -> Result<SavedGame, TextError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Writes a save as text, one `key values...` line per field. `-` stands
+/// for a missing value.
+pub fn to_string(saved: &SavedGame) -> String {
+    let state = &saved.state;
+    let mut text = format!("{MAGIC} {SAVE_VERSION}\n");
+    let mut line = |key: &str, values: String| {
+        writeln!(text, "{key} {values}").unwrap();
+    };
+
+    line("mode", saved.mode.name().to_string());
+    let handling = &saved.handling;
+    line(
+        "handling",
+        format!(
+            "{} {} {}",
+            handling.das, handling.arr, handling.soft_drop_interval
+        ),
+    );
+    line("seed", state.seed.to_string());
+    line("frame", state.frame.to_string());
+    line("game-over", state.game_over.to_string());
+    line(
+        "top-out",
+        optional(state.top_out.map(|top_out| format!("{top_out:?}"))),
+    );
+    line("score", state.score.to_string());
+    line("level", state.level.to_string());
+    line("lines", state.lines.to_string());
+    let statistics = &state.statistics;
+    line(
+        "statistics",
+        format!(
+            "{} {} {} {} {}",
+            statistics.pieces,
+            statistics.biggest_clears,
+            statistics.t_spins,
+            statistics.perfect_clears,
+            statistics.max_combo
+        ),
+    );
+    line(
+        "score-state",
+        format!(
+            "{} {}",
+            state.score_state.back_to_back,
+            optional(state.score_state.combo)
+        ),
+    );
+    line(
+        "held-inputs",
+        join(state.held_inputs.iter().map(|input| format!("{input:?}"))),
+    );
+    line(
+        "timers",
+        format!(
+            "{} {} {:?} {} {} {}",
+            state.shift_timer,
+            state.soft_drop_timer,
+            state.gravity_progress,
+            optional(state.lock_timer),
+            state.lock_resets,
+            state.lowest_row
+        ),
+    );
+    line("randomizer", join(state.randomizer.iter()));
+    line("current", write_block(state.current_block));
+    for &next_block in &state.next_blocks {
+        line("next", write_block(next_block));
+    }
+    line("held", optional(state.held_block.map(write_block)));
+    line("can-hold", state.can_hold.to_string());
+    line("last-rotation-kick", optional(state.last_rotation_kick));
+    line(
+        "last-clear",
+        optional(state.last_clear.map(|(frame, clear)| {
+            format!(
+                "{frame} {} {:?} {}",
+                clear.lines, clear.spin, clear.perfect_clear
+            )
+        })),
+    );
+    for row in &state.cells {
+        line("row", join(row.iter()));
+    }
+    for event in &state.input_log {
+        line(
+            "input",
+            format!("{} {:?} {}", event.frame, event.input, event.pressed),
+        );
+    }
+
+    text
+}
+
+/// Reads a save written by [`to_string`].
+pub fn parse(text: &str) -> Result<SavedGame, TextError> {
+    let fields = Fields::parse(text, MAGIC, SAVE_VERSION)?;
+
+    let mode_name = fields.one("mode")?;
+    let mode = Mode::ALL
+        .into_iter()
+        .find(|mode| [mode.name()] == mode_name.values[..])
+        .ok_or_else(|| mode_name.error("unknown mode"))?;
+
+    let handling = fields.one("handling")?;
+    let [das, arr, soft_drop_interval] = handling.exactly()?;
+    let handling = Handling {
+        das: handling.ranged(das, 0..=MAX_FRAMES)?,
+        arr: handling.ranged(arr, 0..=MAX_FRAMES)?,
+        soft_drop_interval: handling.ranged(soft_drop_interval, 1..=MAX_FRAMES)?,
+    };
+
+    let statistics = fields.one("statistics")?;
+    let [pieces, biggest_clears, t_spins, perfect_clears, max_combo] = statistics.exactly()?;
+    let score_state = fields.one("score-state")?;
+    let [back_to_back, combo] = score_state.exactly()?;
+    let held_inputs = fields.one("held-inputs")?;
+    let timers = fields.one("timers")?;
+    let [shift_timer, soft_drop_timer, gravity_progress, lock_timer, lock_resets, lowest_row] =
+        timers.exactly()?;
+    let top_out = fields.one("top-out")?;
+    let held = fields.one("held")?;
+    let last_clear = fields.one("last-clear")?;
+
+    let state = GameState {
+        seed: fields.one("seed")?.single()?,
+        frame: fields.one("frame")?.single()?,
+        game_over: fields.one("game-over")?.single()?,
+        top_out: match top_out.exactly()? {
+            ["-"] => None,
+            [value] => Some(top_out.named(value, &TOP_OUTS)?),
+        },
+        score: fields.one("score")?.single()?,
+        level: fields.one("level")?.single()?,
+        lines: fields.one("lines")?.single()?,
+        statistics: Statistics {
+            pieces: statistics.value(pieces)?,
+            biggest_clears: statistics.value(biggest_clears)?,
+            t_spins: statistics.value(t_spins)?,
+            perfect_clears: statistics.value(perfect_clears)?,
+            max_combo: statistics.value(max_combo)?,
+        },
+        score_state: ScoreState {
+            back_to_back: score_state.value(back_to_back)?,
+            combo: score_state.optional(combo)?,
+        },
+        held_inputs: held_inputs
+            .values
+            .iter()
+            .map(|value| held_inputs.named(value, &Input::ALL))
+            .collect::<Result<_, _>>()?,
+        shift_timer: timers.value(shift_timer)?,
+        soft_drop_timer: timers.value(soft_drop_timer)?,
+        gravity_progress: timers.value(gravity_progress)?,
+        lock_timer: timers.optional(lock_timer)?,
+        lock_resets: timers.value(lock_resets)?,
+        lowest_row: timers.value(lowest_row)?,
+        cells: fields
+            .all("row")
+            .map(|row| row.values())
+            .collect::<Result<_, _>>()?,
+        randomizer: fields.one("randomizer")?.values()?,
+        current_block: read_block(&fields.one("current")?)?,
+        next_blocks: fields
+            .all("next")
+            .map(|next| read_block(&next))
+            .collect::<Result<_, _>>()?,
+        held_block: match held.values[..] {
+            ["-"] => None,
+            _ => Some(read_block(&held)?),
+        },
+        can_hold: fields.one("can-hold")?.single()?,
+        last_rotation_kick: fields.one("last-rotation-kick")?.single_optional()?,
+        last_clear: match last_clear.values[..] {
+            ["-"] => None,
+            [frame, lines, spin, perfect_clear] => Some((
+                last_clear.value(frame)?,
+                LineClear {
+                    lines: last_clear.value(lines)?,
+                    spin: last_clear.named(spin, &SPINS)?,
+                    perfect_clear: last_clear.value(perfect_clear)?,
+                },
+            )),
+            _ => return Err(last_clear.error("expected 4 values or `-`").into()),
+        },
+        input_log: fields
+            .all("input")
+            .map(|event| {
+                let [frame, input, pressed] = event.exactly()?;
+                Ok(InputEvent {
+                    frame: event.value(frame)?,
+                    input: event.named(input, &Input::ALL)?,
+                    pressed: event.value(pressed)?,
+                })
+            })
+            .collect::<Result<_, Invalid>>()?,
+    };
+
+    Ok(SavedGame {
+        mode,
+        handling,
+        state,
+    })
+}
+
+fn read_block(field: &Field) -> Result<BlockState, Invalid> {
+    let [piece, rotation, row, column] = field.exactly()?;
+    Ok(BlockState {
+        piece: field.value(piece)?,
+        rotation: field.value(rotation)?,
+        row: field.value(row)?,
+        column: field.value(column)?,
+    })
+}
+
+fn write_block(block: BlockState) -> String {
+    format!(
+        "{} {} {} {}",
+        block.piece, block.rotation, block.row, block.column
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualTimeSource, game::Game};
+
+    /// A Marathon game some way in, with a piece held and inputs down.
+    fn game_in_progress() -> Game {
+        let mut game =
+            Game::with_time_source(3, Mode::Marathon.rules(), Box::new(ManualTimeSource::new()));
+        for (index, input) in Input::ALL.iter().cycle().take(60).enumerate() {
+            game.handle_input(*input);
+            for _ in 0..index % 5 {
+                game.tick();
+            }
+            if index % 3 != 0 {
+                game.release_input(*input);
+            }
+        }
+
+        game
+    }
+
+    #[test]
+    fn round_trip() {
+        let game = game_in_progress();
+        let saved = SavedGame {
+            mode: Mode::Marathon,
+            handling: Handling {
+                das: 7,
+                arr: 1,
+                soft_drop_interval: 3,
+            },
+            state: game.state(),
+        };
+        let parsed = parse(&to_string(&saved)).unwrap();
+        assert_eq!(parsed, saved);
+
+        let restored = Game::restore(Mode::Marathon.rules(), &parsed.state).unwrap();
+        assert_eq!(restored.state_hash(), game.state_hash());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let saved = SavedGame {
+            mode: Mode::Marathon,
+            handling: Handling::default(),
+            state: game_in_progress().state(),
+        };
+        let header = format!("{MAGIC} {SAVE_VERSION}");
+        let text = to_string(&saved).replacen(&header, &format!("{MAGIC} 99"), 1);
+        assert!(matches!(
+            parse(&text),
+            Err(TextError::Version { found: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_modes() {
+        let saved = SavedGame {
+            mode: Mode::Marathon,
+            handling: Handling::default(),
+            state: game_in_progress().state(),
+        };
+        let text = to_string(&saved).replace("mode Marathon", "mode Zen");
+        assert!(matches!(
+            parse(&text),
+            Err(TextError::Invalid { line: 2, .. })
+        ));
+    }
+}
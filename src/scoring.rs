@@ -0,0 +1,315 @@
+use std::os::raw::c_int;
+
+/// How the locked piece reached its final position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Spin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+/// What a single lock achieved, as far as scoring is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LineClear {
+    pub lines: c_int,
+    pub spin: Spin,
+    /// The clear left the board completely empty.
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    /// Difficult clears keep a back-to-back chain alive; other line clears
+    /// break it.
+    pub fn is_difficult(&self) -> bool {
+        self.lines >= 4 || (self.lines > 0 && self.spin != Spin::None)
+    }
+}
+
+/// Scoring carried over from one lock to the next.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScoreState {
+    /// Whether the last line clear was a difficult one.
+    pub back_to_back: bool,
+    /// Consecutive line-clearing pieces minus one, or `None` when the last
+    /// piece cleared nothing.
+    pub combo: Option<c_int>,
+}
+
+/// The points a rule set awards. All line clear values are multiplied by
+/// the current level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreTable {
+    /// Points for clearing 0, 1, 2 and so on lines. The last entry is the
+    /// biggest clear the piece set can make.
+    pub line_clears: Vec<c_int>,
+    /// Name of the biggest clear, such as "Tetris".
+    pub biggest_clear: &'static str,
+    /// T-spin mini points for clearing 0, 1 and 2 lines.
+    pub t_spin_mini: [c_int; 3],
+    /// T-spin points for clearing 0 to 3 lines.
+    pub t_spin: [c_int; 4],
+    /// Perfect clear bonus for clearing 0, 1, 2 and so on lines, up to the
+    /// biggest clear.
+    pub perfect_clear: Vec<c_int>,
+    /// Perfect clear bonus for a biggest clear that continues a
+    /// back-to-back chain, replacing the plain bonus.
+    pub back_to_back_perfect_clear: c_int,
+    /// Points per row for a soft drop.
+    pub soft_drop: c_int,
+    /// Points per row for a hard drop.
+    pub hard_drop: c_int,
+    /// Points per combo step.
+    pub combo: c_int,
+    /// Percentage a difficult clear is worth when it continues a
+    /// back-to-back chain.
+    pub back_to_back_percent: c_int,
+}
+
+impl Default for ScoreTable {
+    fn default() -> Self {
+        Self {
+            line_clears: vec![0, 100, 300, 500, 800],
+            biggest_clear: "Tetris",
+            t_spin_mini: [100, 200, 400],
+            t_spin: [400, 800, 1200, 1600],
+            perfect_clear: vec![0, 800, 1200, 1800, 2000],
+            back_to_back_perfect_clear: 3200,
+            soft_drop: 1,
+            hard_drop: 2,
+            combo: 50,
+            back_to_back_percent: 150,
+        }
+    }
+}
+
+impl ScoreTable {
+    /// The scoring for the pentomino set, which can clear five lines at
+    /// once.
+    pub fn pentris() -> Self {
+        Self {
+            line_clears: vec![0, 100, 300, 500, 800, 1200],
+            biggest_clear: "Pentris",
+            perfect_clear: vec![0, 800, 1200, 1800, 2000, 2400],
+            ..Self::default()
+        }
+    }
+
+    /// The most lines a single piece can clear under this table.
+    pub fn biggest_clear_lines(&self) -> c_int {
+        self.line_clears.len() as c_int - 1
+    }
+
+    /// A short name for `clear`, such as "T-SPIN DOUBLE", or `None` when it
+    /// is not worth announcing.
+    pub fn label(&self, clear: LineClear) -> Option<String> {
+        let lines = match clear.lines {
+            1 => " SINGLE",
+            2 => " DOUBLE",
+            3 => " TRIPLE",
+            _ => "",
+        };
+
+        if clear.perfect_clear {
+            return Some("PERFECT CLEAR".to_string());
+        }
+
+        match clear.spin {
+            Spin::Full => Some(format!("T-SPIN{lines}")),
+            Spin::Mini => Some(format!("T-SPIN MINI{lines}")),
+            Spin::None if clear.lines >= self.biggest_clear_lines() => {
+                Some(self.biggest_clear.to_uppercase())
+            }
+            Spin::None => None,
+        }
+    }
+
+    /// Scores a piece locking with `clear`, updating the back-to-back and
+    /// combo state. Points stop at `c_int::MAX` instead of overflowing.
+    pub fn score_lock(&self, state: &mut ScoreState, clear: LineClear, level: c_int) -> c_int {
+        if clear.lines == 0 {
+            state.combo = None;
+            return self.line_clear_points(clear).saturating_mul(level);
+        }
+
+        // The perfect clear bonus is added after the back-to-back bonus, so
+        // it is not multiplied by it.
+        let perfect_clear_points = if clear.perfect_clear {
+            self.perfect_clear_points(clear, state.back_to_back)
+                .saturating_mul(level)
+        } else {
+            0
+        };
+
+        let mut points = self.line_clear_points(clear).saturating_mul(level);
+        if clear.is_difficult() {
+            if state.back_to_back {
+                points = points.saturating_mul(self.back_to_back_percent) / 100;
+            }
+            state.back_to_back = true;
+        } else {
+            state.back_to_back = false;
+        }
+        points = points.saturating_add(perfect_clear_points);
+
+        let combo = state.combo.map_or(0, |combo| combo.saturating_add(1));
+        state.combo = Some(combo);
+        points.saturating_add(self.combo.saturating_mul(combo).saturating_mul(level))
+    }
+
+    pub fn soft_drop_points(&self, rows: c_int) -> c_int {
+        self.soft_drop.saturating_mul(rows)
+    }
+
+    pub fn hard_drop_points(&self, rows: c_int) -> c_int {
+        self.hard_drop.saturating_mul(rows)
+    }
+
+    fn perfect_clear_points(&self, clear: LineClear, back_to_back: bool) -> c_int {
+        if clear.lines >= self.biggest_clear_lines() && back_to_back {
+            return self.back_to_back_perfect_clear;
+        }
+
+        self.perfect_clear
+            .get(clear.lines as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn line_clear_points(&self, clear: LineClear) -> c_int {
+        let table: &[c_int] = match clear.spin {
+            Spin::None => &self.line_clears,
+            Spin::Mini => &self.t_spin_mini,
+            Spin::Full => &self.t_spin,
+        };
+
+        table.get(clear.lines as usize).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: c_int, spin: Spin) -> LineClear {
+        LineClear {
+            lines,
+            spin,
+            perfect_clear: false,
+        }
+    }
+
+    fn perfect(lines: c_int) -> LineClear {
+        LineClear {
+            perfect_clear: true,
+            ..clear(lines, Spin::None)
+        }
+    }
+
+    #[test]
+    fn line_clears_scale_with_level() {
+        let table = ScoreTable::default();
+        let mut state = ScoreState::default();
+        assert_eq!(table.score_lock(&mut state, clear(1, Spin::None), 3), 300);
+        assert_eq!(table.score_lock(&mut state, clear(0, Spin::None), 3), 0);
+        assert_eq!(table.score_lock(&mut state, clear(2, Spin::Full), 2), 2400);
+    }
+
+    #[test]
+    fn back_to_back_tetrises() {
+        let table = ScoreTable::default();
+        let mut state = ScoreState::default();
+        assert_eq!(table.score_lock(&mut state, clear(4, Spin::None), 1), 800);
+        assert!(state.back_to_back);
+        // 800 * 1.5, plus a one step combo.
+        assert_eq!(table.score_lock(&mut state, clear(4, Spin::None), 1), 1250);
+    }
+
+    #[test]
+    fn easy_clear_breaks_back_to_back() {
+        let table = ScoreTable::default();
+        let mut state = ScoreState {
+            back_to_back: true,
+            combo: None,
+        };
+        assert_eq!(table.score_lock(&mut state, clear(2, Spin::None), 1), 300);
+        assert!(!state.back_to_back);
+    }
+
+    #[test]
+    fn spin_without_lines_keeps_back_to_back() {
+        let table = ScoreTable::default();
+        let mut state = ScoreState {
+            back_to_back: true,
+            combo: Some(2),
+        };
+        assert_eq!(table.score_lock(&mut state, clear(0, Spin::Full), 1), 400);
+        assert!(state.back_to_back);
+        assert_eq!(state.combo, None);
+    }
+
+    #[test]
+    fn combos_add_up_until_a_piece_clears_nothing() {
+        let table = ScoreTable::default();
+        let mut state = ScoreState::default();
+        let single = clear(1, Spin::None);
+        assert_eq!(table.score_lock(&mut state, single, 1), 100);
+        assert_eq!(table.score_lock(&mut state, single, 1), 150);
+        assert_eq!(table.score_lock(&mut state, single, 1), 200);
+        table.score_lock(&mut state, clear(0, Spin::None), 1);
+        assert_eq!(table.score_lock(&mut state, single, 1), 100);
+    }
+
+    #[test]
+    fn perfect_clear_bonus() {
+        let table = ScoreTable::default();
+        let mut state = ScoreState::default();
+        assert_eq!(table.score_lock(&mut state, perfect(1), 2), 1800);
+
+        let mut state = ScoreState::default();
+        assert_eq!(table.score_lock(&mut state, perfect(4), 1), 2800);
+    }
+
+    #[test]
+    fn back_to_back_perfect_clear_is_not_multiplied() {
+        let table = ScoreTable::default();
+        let mut state = ScoreState {
+            back_to_back: true,
+            combo: None,
+        };
+        // 800 * 1.5 + 3200.
+        assert_eq!(table.score_lock(&mut state, perfect(4), 1), 4400);
+    }
+
+    #[test]
+    fn labels() {
+        let table = ScoreTable::default();
+        let label = |clear| table.label(clear);
+        assert_eq!(label(clear(4, Spin::None)).as_deref(), Some("TETRIS"));
+        assert_eq!(
+            label(clear(1, Spin::Mini)).as_deref(),
+            Some("T-SPIN MINI SINGLE")
+        );
+        assert_eq!(label(perfect(2)).as_deref(), Some("PERFECT CLEAR"));
+        assert_eq!(label(clear(3, Spin::None)), None);
+    }
+
+    #[test]
+    fn pentris_scores_five_lines_as_its_biggest_clear() {
+        let table = ScoreTable::pentris();
+        assert_eq!(
+            table.label(clear(5, Spin::None)).as_deref(),
+            Some("PENTRIS")
+        );
+        assert_eq!(table.label(clear(4, Spin::None)), None);
+
+        let mut state = ScoreState::default();
+        assert_eq!(table.score_lock(&mut state, clear(5, Spin::None), 1), 1200);
+        let mut state = ScoreState {
+            back_to_back: true,
+            combo: None,
+        };
+        // 1200 * 1.5 + 3200.
+        assert_eq!(table.score_lock(&mut state, perfect(5), 1), 5000);
+    }
+}
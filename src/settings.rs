@@ -0,0 +1,83 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use crate::{
+    handling::{Handling, MAX_FRAMES},
+    text::{Fields, TextError},
+};
+
+/// Version written to new settings files. Files of any other version are
+/// rejected.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// First word of every settings file.
+const MAGIC: &str = "tetris-settings";
+
+/// A player's preferences, kept between sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Settings {
+    pub handling: Handling,
+}
+
+pub fn write(path: impl AsRef<Path>, settings: &Settings) -> io::Result<()> {
+    fs::write(path, to_string(settings))
+}
+
+pub fn read(path: impl AsRef<Path>) -> Result<Settings, TextError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Writes settings as text, one `key value` line each. Handling values are
+/// in frames at 60 per second.
+pub fn to_string(settings: &Settings) -> String {
+    let handling = &settings.handling;
+    let mut text = format!("{MAGIC} {SETTINGS_VERSION}\n");
+    let mut line = |key: &str, value: u32| {
+        writeln!(text, "{key} {value}").unwrap();
+    };
+
+    line("das", handling.das);
+    line("arr", handling.arr);
+    line("soft-drop", handling.soft_drop_interval);
+
+    text
+}
+
+/// Reads settings written by [`to_string`].
+pub fn parse(text: &str) -> Result<Settings, TextError> {
+    let fields = Fields::parse(text, MAGIC, SETTINGS_VERSION)?;
+
+    let handling = Handling {
+        das: fields.one("das")?.single_ranged(0..=MAX_FRAMES)?,
+        arr: fields.one("arr")?.single_ranged(0..=MAX_FRAMES)?,
+        soft_drop_interval: fields.one("soft-drop")?.single_ranged(1..=MAX_FRAMES)?,
+    };
+
+    Ok(Settings { handling })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            handling: Handling {
+                das: 8,
+                arr: 0,
+                soft_drop_interval: 3,
+            },
+        };
+        assert_eq!(parse(&to_string(&settings)).unwrap(), settings);
+    }
+
+    #[test]
+    fn rejects_values_out_of_range() {
+        let text = to_string(&Settings::default());
+        for (key, bad) in [("das", "das 61"), ("soft-drop", "soft-drop 0")] {
+            let line = text.lines().find(|line| line.starts_with(key)).unwrap();
+            let result = parse(&text.replace(line, bad));
+            assert!(matches!(result, Err(TextError::Invalid { .. })), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::os::raw::c_int;
+
+use crate::scoring::{LineClear, Spin};
+
+/// Running totals for the results screen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub pieces: u32,
+    /// Clears of the most lines the piece set can make: tetrises, or
+    /// pentrises with pentominoes.
+    pub biggest_clears: u32,
+    pub t_spins: u32,
+    pub perfect_clears: u32,
+    pub max_combo: c_int,
+}
+
+impl Statistics {
+    /// Records a locked piece and what it cleared. `combo` is the combo
+    /// count after the lock, if the piece continued one, and
+    /// `biggest_clear` the most lines the rule set can clear at once.
+    pub fn record_lock(&mut self, clear: LineClear, combo: Option<c_int>, biggest_clear: c_int) {
+        self.pieces += 1;
+
+        if clear.lines >= biggest_clear {
+            self.biggest_clears += 1;
+        }
+        if clear.spin != Spin::None {
+            self.t_spins += 1;
+        }
+        if clear.perfect_clear {
+            self.perfect_clears += 1;
+        }
+        if let Some(combo) = combo {
+            self.max_combo = self.max_combo.max(combo);
+        }
+    }
+}
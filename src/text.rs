@@ -0,0 +1,225 @@
+//! The line-based `key values...` text format shared by saves, replays and
+//! settings, and the error all of the game's data files are read with.
+
+use std::{error::Error, fmt, io, ops::RangeInclusive, str::FromStr};
+
+/// Why a data file could not be read.
+#[derive(Debug)]
+pub enum TextError {
+    Io(io::Error),
+    /// The file was written by a different version of the game than the
+    /// `expected` one.
+    Version {
+        found: u32,
+        expected: u32,
+    },
+    /// The file is malformed at the given 1-based line.
+    Invalid {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextError::Io(error) => write!(f, "{error}"),
+            TextError::Version { found, expected } => write!(
+                f,
+                "file is from version {found}, only version {expected} can be read"
+            ),
+            TextError::Invalid { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for TextError {}
+
+impl From<io::Error> for TextError {
+    fn from(error: io::Error) -> Self {
+        TextError::Io(error)
+    }
+}
+
+impl From<Invalid> for TextError {
+    fn from(invalid: Invalid) -> Self {
+        TextError::Invalid {
+            line: invalid.line,
+            message: invalid.message,
+        }
+    }
+}
+
+/// A malformed line, numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Invalid {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Invalid {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// The lines of a file after its `MAGIC VERSION` header.
+pub(crate) struct Fields<'a> {
+    lines: Vec<(usize, &'a str, Vec<&'a str>)>,
+}
+
+/// One `key values...` line.
+pub(crate) struct Field<'a> {
+    pub line: usize,
+    pub key: &'a str,
+    pub values: Vec<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    /// Splits `text` into fields after checking its header names `magic`
+    /// and `version`. Blank lines are skipped.
+    pub fn parse(text: &'a str, magic: &str, version: u32) -> Result<Self, TextError> {
+        let mut lines = (1..)
+            .zip(text.lines())
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let found = lines.next().and_then(|(_, header)| {
+            match header.split_whitespace().collect::<Vec<_>>()[..] {
+                [word, found] if word == magic => found.parse().ok(),
+                _ => None,
+            }
+        });
+        let Some(found) = found else {
+            return Err(Invalid::new(1, format!("not a `{magic}` file")).into());
+        };
+        if found != version {
+            return Err(TextError::Version {
+                found,
+                expected: version,
+            });
+        }
+
+        let lines = lines
+            .map(|(number, line)| {
+                let mut words = line.split_whitespace();
+                let key = words.next().unwrap();
+                (number, key, words.collect())
+            })
+            .collect();
+
+        Ok(Self { lines })
+    }
+
+    pub fn all<'b>(&'b self, key: &'b str) -> impl Iterator<Item = Field<'a>> + 'b {
+        self.lines
+            .iter()
+            .filter(move |(_, line_key, _)| *line_key == key)
+            .map(|(line, key, values)| Field {
+                line: *line,
+                key,
+                values: values.clone(),
+            })
+    }
+
+    /// The line for `key`, which must appear exactly once.
+    pub fn one(&self, key: &str) -> Result<Field<'a>, Invalid> {
+        let mut found = self.all(key);
+        match (found.next(), found.next()) {
+            (Some(field), None) => Ok(field),
+            (Some(field), Some(_)) => Err(field.error("appears more than once")),
+            (None, _) => Err(Invalid::new(
+                self.lines.last().map_or(1, |line| line.0),
+                format!("`{key}` is missing"),
+            )),
+        }
+    }
+}
+
+impl<'a> Field<'a> {
+    pub fn error(&self, message: &str) -> Invalid {
+        Invalid::new(self.line, format!("`{}`: {message}", self.key))
+    }
+
+    pub fn exactly<const N: usize>(&self) -> Result<[&'a str; N], Invalid> {
+        self.values[..]
+            .try_into()
+            .map_err(|_| self.error(&format!("expected {N} values")))
+    }
+
+    pub fn value<T: FromStr>(&self, value: &str) -> Result<T, Invalid> {
+        value
+            .parse()
+            .map_err(|_| self.error(&format!("`{value}` is not a valid value")))
+    }
+
+    /// Parses a value that must lie within `range`.
+    pub fn ranged<T: FromStr + PartialOrd + fmt::Display>(
+        &self,
+        value: &str,
+        range: RangeInclusive<T>,
+    ) -> Result<T, Invalid> {
+        let parsed = self.value(value)?;
+        if !range.contains(&parsed) {
+            return Err(self.error(&format!(
+                "`{value}` is not between {} and {}",
+                range.start(),
+                range.end()
+            )));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parses every value on the line.
+    pub fn values<T: FromStr>(&self) -> Result<Vec<T>, Invalid> {
+        self.values.iter().map(|value| self.value(value)).collect()
+    }
+
+    pub fn optional<T: FromStr>(&self, value: &str) -> Result<Option<T>, Invalid> {
+        match value {
+            "-" => Ok(None),
+            _ => self.value(value).map(Some),
+        }
+    }
+
+    pub fn single<T: FromStr>(&self) -> Result<T, Invalid> {
+        let [value] = self.exactly()?;
+        self.value(value)
+    }
+
+    pub fn single_ranged<T: FromStr + PartialOrd + fmt::Display>(
+        &self,
+        range: RangeInclusive<T>,
+    ) -> Result<T, Invalid> {
+        let [value] = self.exactly()?;
+        self.ranged(value, range)
+    }
+
+    pub fn single_optional<T: FromStr>(&self) -> Result<Option<T>, Invalid> {
+        let [value] = self.exactly()?;
+        self.optional(value)
+    }
+
+    /// Looks a value up by its `Debug` name in `all`.
+    pub fn named<T: fmt::Debug + Copy>(&self, value: &str, all: &[T]) -> Result<T, Invalid> {
+        all.iter()
+            .copied()
+            .find(|entry| format!("{entry:?}") == value)
+            .ok_or_else(|| self.error(&format!("`{value}` is not a valid value")))
+    }
+}
+
+/// Writes `value`, or `-` for `None`.
+pub(crate) fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+pub(crate) fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}